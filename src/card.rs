@@ -0,0 +1,192 @@
+//! Card model
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Suit {
+    Clubs,
+    Spades,
+    Hearts,
+    Diamonds,
+}
+
+impl Suit {
+    /// Suits in deck order.
+    pub const ALL: [Suit; 4] = [Suit::Clubs, Suit::Spades, Suit::Hearts, Suit::Diamonds];
+
+    pub fn color(self) -> Color {
+        match self {
+            Suit::Clubs | Suit::Spades => Color::Black,
+            Suit::Hearts | Suit::Diamonds => Color::Red,
+        }
+    }
+
+    /// Name of the folder holding the suit's images.
+    pub fn name(self) -> &'static str {
+        match self {
+            Suit::Clubs => "clubs",
+            Suit::Spades => "spades",
+            Suit::Hearts => "hearts",
+            Suit::Diamonds => "diamonds",
+        }
+    }
+
+    pub fn symbol(self) -> char {
+        match self {
+            Suit::Clubs => '♣',
+            Suit::Spades => '♠',
+            Suit::Hearts => '♥',
+            Suit::Diamonds => '♦',
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Color {
+    Black,
+    Red,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rank {
+    Ace = 1,
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Eight,
+    Nine,
+    Ten,
+    Jack,
+    Queen,
+    King,
+}
+
+impl Rank {
+    pub const ALL: [Rank; 13] = [
+        Rank::Ace, Rank::Two, Rank::Three, Rank::Four, Rank::Five, Rank::Six, Rank::Seven,
+        Rank::Eight, Rank::Nine, Rank::Ten, Rank::Jack, Rank::Queen, Rank::King,
+    ];
+
+    /// Ace is 1 up to king at 13.
+    pub fn value(self) -> u8 {
+        self as u8
+    }
+
+    /// True when this rank is directly below `other`, as a six is below a seven.
+    pub fn is_below(self, other: Rank) -> bool {
+        self.value() + 1 == other.value()
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Rank::Ace => "A",
+            Rank::Two => "2",
+            Rank::Three => "3",
+            Rank::Four => "4",
+            Rank::Five => "5",
+            Rank::Six => "6",
+            Rank::Seven => "7",
+            Rank::Eight => "8",
+            Rank::Nine => "9",
+            Rank::Ten => "10",
+            Rank::Jack => "J",
+            Rank::Queen => "Q",
+            Rank::King => "K",
+        }
+    }
+}
+
+/// Suit and rank packed in one byte, the position of the card in an
+/// unshuffled deck.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CardId(u8);
+
+impl CardId {
+    pub fn new(suit: Suit, rank: Rank) -> CardId {
+        CardId(suit as u8 * 13 + rank.value() - 1)
+    }
+
+    pub fn suit(self) -> Suit {
+        Suit::ALL[self.0 as usize / 13]
+    }
+
+    pub fn rank(self) -> Rank {
+        Rank::ALL[self.0 as usize % 13]
+    }
+
+    pub fn color(self) -> Color {
+        self.suit().color()
+    }
+
+    /// Position in an unshuffled deck, used to look up the card's image.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl fmt::Display for CardId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.rank().symbol(), self.suit().symbol())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Card {
+    pub id: CardId,
+    pub visible: bool,
+}
+
+impl Card {
+    pub fn suit(&self) -> Suit {
+        self.id.suit()
+    }
+
+    pub fn rank(&self) -> Rank {
+        self.id.rank()
+    }
+
+    pub fn color(&self) -> Color {
+        self.id.color()
+    }
+}
+
+/// Returns the 52 cards in suit order, ace to king.
+pub fn deck() -> Vec<Card> {
+    let mut cards: Vec<Card> = Vec::with_capacity(52);
+
+    for suit in Suit::ALL {
+        for rank in Rank::ALL {
+            cards.push(Card {
+                id: CardId::new(suit, rank),
+                visible: false,
+            });
+        }
+    }
+
+    cards
+}
+
+#[test]
+fn test_card_id() {
+    for (i, card) in deck().iter().enumerate() {
+        assert_eq!(i, card.id.index());
+        assert_eq!(card.id, CardId::new(card.suit(), card.rank()));
+    }
+
+    let queen = CardId::new(Suit::Hearts, Rank::Queen);
+    assert_eq!("Q♥", queen.to_string());
+    assert_eq!(Color::Red, queen.color());
+    assert_eq!("10♣", CardId::new(Suit::Clubs, Rank::Ten).to_string());
+}
+
+#[test]
+fn test_rank() {
+    assert_eq!(1, Rank::Ace.value());
+    assert_eq!(13, Rank::King.value());
+    assert!(Rank::Six.is_below(Rank::Seven));
+    assert!(!Rank::Seven.is_below(Rank::Six));
+}
@@ -0,0 +1,85 @@
+//! Game clock
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Time played on a game, counted from the first move until the game is won.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Clock {
+    elapsed: Duration,
+    running: bool,
+    paused: bool,
+}
+
+impl Clock {
+    /// Starts counting, called on the first move of a game.
+    pub fn start(&mut self) {
+        self.running = true;
+        self.paused = false;
+    }
+
+    /// Stops for good, the elapsed time is kept.
+    pub fn stop(&mut self) {
+        self.running = false;
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// True while time is being counted.
+    pub fn is_ticking(&self) -> bool {
+        self.running && !self.paused
+    }
+
+    pub fn tick(&mut self, by: Duration) {
+        if self.is_ticking() {
+            self.elapsed += by;
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Minutes and seconds, hours are added once the game runs that long.
+    pub fn format(&self) -> String {
+        let seconds = self.elapsed().as_secs();
+        if seconds >= 3600 {
+            format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+        } else {
+            format!("{:02}:{:02}", seconds / 60, seconds % 60)
+        }
+    }
+}
+
+#[test]
+fn test_clock() {
+    let mut clock = Clock::default();
+
+    // nothing is counted before the first move
+    clock.tick(Duration::from_secs(5));
+    assert_eq!(Duration::ZERO, clock.elapsed());
+
+    clock.start();
+    clock.tick(Duration::from_secs(65));
+    assert_eq!("01:05", clock.format());
+
+    clock.set_paused(true);
+    clock.tick(Duration::from_secs(5));
+    clock.set_paused(false);
+    clock.tick(Duration::from_secs(3600));
+    assert_eq!("1:01:05", clock.format());
+
+    clock.stop();
+    clock.tick(Duration::from_secs(5));
+    assert_eq!(Duration::from_secs(3665), clock.elapsed());
+}
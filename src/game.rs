@@ -0,0 +1,830 @@
+//! Game state and move engine
+//!
+//! `Game` owns the deck and every pile, shared by all the variants, and
+//! checks and plays moves through the rules of its variant. It knows
+//! nothing about pixels, caches or image handles, so a game can be driven
+//! without iced.
+use std::fmt;
+
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::card::{deck, Card, CardId, Rank};
+#[cfg(test)]
+use crate::card::Suit;
+use crate::score::Scoring;
+use crate::variant::VariantKind;
+
+/// Highest deal number handed out for a random game.
+pub const MAX_RANDOM_DEAL: u32 = 999_999;
+
+/// A single player action, independent of how it was requested.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Move {
+    StockToWaste,
+    /// Turns the waste back over onto the empty stock.
+    Recycle,
+    WasteToTableau(usize),
+    WasteToFoundation(usize),
+    /// Moves the top `depth` cards of a column onto another column.
+    TableauToTableau { from: usize, depth: usize, to: usize },
+    TableauToFoundation { from: usize, to: usize },
+    /// Takes the top card of a foundation back down onto a column.
+    FoundationToTableau { from: usize, to: usize },
+    /// Parks the top card of a column in a free cell.
+    TableauToCell { from: usize, to: usize },
+    CellToTableau { from: usize, to: usize },
+    CellToFoundation { from: usize, to: usize },
+    /// Deals a card from the stock onto every column.
+    DealRow,
+    /// Discards two uncovered cards of the tableau adding up to 13.
+    TableauPair { from: usize, with: usize },
+    /// Discards the waste card with an uncovered card adding up to 13.
+    WastePair(usize),
+}
+
+/// The reason a move was rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveError {
+    WrongColor,
+    WrongRank,
+    WrongSuit,
+    NotKingOnEmpty,
+    NotAceOnEmpty,
+    FaceDown,
+    BadDepth,
+    EmptySource,
+    StockNotEmpty,
+    SameColumn,
+    NoFoundation,
+    NoPassesLeft,
+    CellTaken,
+    NotSequence,
+    TooManyCards,
+    NotInVariant,
+    EmptyColumn,
+    Covered,
+    NotKing,
+    NotThirteen,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            MoveError::WrongColor => "Cards must alternate in color",
+            MoveError::WrongRank => "Card is not the next value",
+            MoveError::WrongSuit => "Foundations are built up by suit",
+            MoveError::NotKingOnEmpty => "Only a king can go on an empty column",
+            MoveError::NotAceOnEmpty => "Only an ace can start a foundation",
+            MoveError::FaceDown => "Face down cards can not be moved",
+            MoveError::BadDepth => "There are not that many cards in the column",
+            MoveError::EmptySource => "There is no card to move",
+            MoveError::StockNotEmpty => "The waste can only go back once the stock is empty",
+            MoveError::SameColumn => "Cards are already in that column",
+            MoveError::NoFoundation => "No foundation takes this card",
+            MoveError::NoPassesLeft => "There are no passes through the stock left",
+            MoveError::CellTaken => "The free cell already holds a card",
+            MoveError::NotSequence => "Only cards in sequence move together",
+            MoveError::TooManyCards => "Not enough free cells and empty columns to move that many cards",
+            MoveError::NotInVariant => "This game does not have that move",
+            MoveError::EmptyColumn => "Every column needs a card before a row is dealt",
+            MoveError::Covered => "The card is covered by another",
+            MoveError::NotKing => "Only a king is removed on its own",
+            MoveError::NotThirteen => "The two cards must add up to 13",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+/// How many times the player may go through the stock.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Passes {
+    #[default]
+    Unlimited,
+    One,
+    Three,
+}
+
+impl Passes {
+    pub const ALL: [Passes; 3] = [Passes::Unlimited, Passes::One, Passes::Three];
+
+    pub fn limit(self) -> Option<u16> {
+        match self {
+            Passes::Unlimited => None,
+            Passes::One => Some(1),
+            Passes::Three => Some(3),
+        }
+    }
+}
+
+impl fmt::Display for Passes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.limit() {
+            Some(limit) => write!(f, "{}", limit),
+            None => write!(f, "Unlimited"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Game {
+    /// The rules the game is played by.
+    pub variant: VariantKind,
+    /// Deal number the deck was shuffled from, 0 for a hand made deck.
+    pub deal: u32,
+    pub cards: Vec<Card>,
+
+    pub tab_card_indexes: Vec<Vec<usize>>,
+    pub foundation_cards: Vec<Option<usize>>,
+    pub stock_card_indexes: Vec<usize>,
+    pub waste_card_indexes: Vec<usize>,
+    /// Free cells holding one card each, only FreeCell has them.
+    pub cell_cards: Vec<Option<usize>>,
+
+    pub card_rounds: u16,
+    pub cards_moved_from_waste: u16,
+    pub cards_to_play: usize,
+    pub passes: Passes,
+
+    pub scoring: Scoring,
+    pub score: i32,
+    /// Moves played to reach the position.
+    pub moves: u32,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game {
+            variant: VariantKind::default(),
+            deal: 0,
+            cards: vec![],
+            tab_card_indexes: vec![vec![]; 7],
+            foundation_cards: vec![None; 4],
+            stock_card_indexes: vec![],
+            waste_card_indexes: vec![],
+            cell_cards: vec![],
+            card_rounds: 0,
+            cards_moved_from_waste: 0,
+            cards_to_play: 3,
+            passes: Passes::default(),
+            scoring: Scoring::default(),
+            score: 0,
+            moves: 0,
+        }
+    }
+}
+
+impl Game {
+    /// Deals a game of the variant from the given deck order.
+    pub fn new(variant: VariantKind, cards: Vec<Card>, cards_to_play: usize) -> Game {
+        let rules = variant.rules();
+        let layout = rules.layout();
+        let mut game = Game {
+            variant,
+            cards,
+            cards_to_play,
+            tab_card_indexes: vec![vec![]; layout.columns],
+            foundation_cards: vec![None; layout.foundations],
+            cell_cards: vec![None; layout.cells],
+            ..Default::default()
+        };
+        rules.deal(&mut game);
+
+        game
+    }
+
+    /// Deals the given deal number, the same number always gives the same game.
+    pub fn deal(variant: VariantKind, deal: u32, cards_to_play: usize) -> Game {
+        Game {
+            deal,
+            ..Game::new(variant, variant.rules().cards(deal), cards_to_play)
+        }
+    }
+
+    /// Deals a randomly picked deal number.
+    pub fn shuffled(variant: VariantKind, cards_to_play: usize) -> Game {
+        Game::deal(variant, thread_rng().gen_range(1..=variant.rules().max_deal()), cards_to_play)
+    }
+
+    /// Scores the game from the start with the given rules.
+    pub fn with_scoring(self, scoring: Scoring) -> Game {
+        Game {
+            scoring,
+            score: scoring.opening(self.cards.len()),
+            ..self
+        }
+    }
+
+    /// Limits the passes through the stock.
+    pub fn with_passes(self, passes: Passes) -> Game {
+        Game {
+            passes,
+            ..self
+        }
+    }
+
+    /// True while the waste may still be turned back over, the first pass
+    /// is the deal itself and each recycle starts another.
+    pub fn has_passes_left(&self) -> bool {
+        self.passes.limit().is_none_or(|limit| self.card_rounds + 1 < limit)
+    }
+
+    /// The variant's win condition is met.
+    pub fn is_won(&self) -> bool {
+        self.variant.rules().is_won(self)
+    }
+
+    /// The stock and waste are played out and every column is face up and
+    /// going down in rank, so the rest of the cards can go up without any
+    /// choice left, in the games where cards go up one at a time.
+    pub fn can_auto_finish(&self) -> bool {
+        self.variant.rules().auto_finishes()
+            && self.stock_card_indexes.is_empty()
+            && self.waste_card_indexes.is_empty()
+            && self.tab_card_indexes.iter().all(|tab| {
+                tab.iter().all(|index| self.cards[*index].visible)
+                    && tab.windows(2).all(|pair| self.cards[pair[1]].rank() < self.cards[pair[0]].rank())
+            })
+            && !self.is_won()
+            && self.finish_move().is_some()
+    }
+
+    /// The next card to play to a foundation when finishing, the lowest first.
+    pub fn finish_move(&self) -> Option<Move> {
+        let foundations = 0..self.foundation_cards.len();
+        let from_tableau = self.tab_card_indexes.iter().enumerate()
+            .filter_map(|(from, tab)| tab.last().map(|index| (from, *index)))
+            .flat_map(|(from, index)| {
+                foundations.clone().map(move |to| (index, Move::TableauToFoundation { from, to }))
+            });
+        let from_cells = self.cell_cards.iter().enumerate()
+            .filter_map(|(from, cell)| cell.map(|index| (from, index)))
+            .flat_map(|(from, index)| {
+                foundations.clone().map(move |to| (index, Move::CellToFoundation { from, to }))
+            });
+
+        from_tableau.chain(from_cells)
+            .filter(|(_, mv)| self.check(*mv).is_ok())
+            .min_by_key(|(index, _)| self.cards[*index].rank())
+            .map(|(_, mv)| mv)
+    }
+
+    /// No productive move is left anywhere the stock can reach, so drawing
+    /// and recycling can not change anything and the game is lost.
+    pub fn is_dead(&self) -> bool {
+        self.variant.rules().finds_lost_games() && !self.is_won() && self.productive_moves().is_empty()
+    }
+
+    /// Moves that get the game somewhere, found by going once around the
+    /// stock. What counts is up to `Variant::is_productive`.
+    pub fn productive_moves(&self) -> Vec<Move> {
+        let mut game = self.clone();
+        let mut seen = vec![];
+        let mut moves = vec![];
+
+        loop {
+            let rules = game.variant.rules();
+            for mv in game.legal_moves() {
+                // the same move often comes round again with the stock
+                if !moves.contains(&mv) && rules.is_productive(&game, mv) {
+                    moves.push(mv);
+                }
+            }
+
+            let draw = if game.stock_card_indexes.is_empty() { Move::Recycle } else { Move::StockToWaste };
+            if game.apply(draw).is_err() {
+                return moves
+            }
+            let piles = (game.stock_card_indexes.clone(), game.waste_card_indexes.clone());
+            if seen.contains(&piles) {
+                return moves
+            }
+            seen.push(piles);
+        }
+    }
+
+    /// Returns every move that is legal in the current position.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.variant.rules().candidate_moves(self).into_iter()
+            .filter(|mv| self.check(*mv).is_ok())
+            .collect()
+    }
+
+    /// Validates the move without playing it.
+    pub fn check(&self, mv: Move) -> Result<(), MoveError> {
+        self.variant.rules().check(self, mv)
+    }
+
+    /// Plays the move, the game is left untouched if the move is not legal.
+    pub fn apply(&mut self, mv: Move) -> Result<(), MoveError> {
+        self.check(mv)?;
+
+        let played = self.variant.rules().apply(self, mv);
+
+        let points = self.scoring.points(mv, played.flipped, self.cards_to_play)
+            + self.scoring.foundation_points(played.to_foundations);
+        self.score = self.scoring.add(self.score, points);
+        self.moves += 1;
+
+        Ok(())
+    }
+
+    // the card of the same suit one lower, what a foundation shows once
+    // its top card is taken off
+    pub fn card_below(&self, card_index: usize) -> Option<usize> {
+        let card = &self.cards[card_index];
+        let below = Rank::ALL.iter().find(|rank| rank.is_below(card.rank()))?;
+        let id = CardId::new(card.suit(), *below);
+        self.cards.iter().position(|card| card.id == id)
+    }
+
+    // a king starts an empty column, otherwise the card must be one less
+    // and the opposite color of the last card
+    pub fn check_tableau(&self, card_index: usize, tab_index: usize) -> Result<(), MoveError> {
+        let card = &self.cards[card_index];
+
+        let Some(&last_card_index) = self.tab_card_indexes[tab_index].last() else {
+            if card.rank() != Rank::King {
+                return Err(MoveError::NotKingOnEmpty)
+            }
+            return Ok(())
+        };
+
+        let last_card = &self.cards[last_card_index];
+        if last_card.color() == card.color() {
+            return Err(MoveError::WrongColor)
+        }
+        if !card.rank().is_below(last_card.rank()) {
+            return Err(MoveError::WrongRank)
+        }
+        Ok(())
+    }
+
+    // an ace starts an empty foundation, otherwise the card must be
+    // the next value of the same suit
+    pub fn check_foundation(&self, card_index: usize, fd_index: usize) -> Result<(), MoveError> {
+        let card = &self.cards[card_index];
+
+        let Some(fd_card_index) = self.foundation_cards[fd_index] else {
+            if card.rank() != Rank::Ace {
+                return Err(MoveError::NotAceOnEmpty)
+            }
+            return Ok(())
+        };
+
+        let fd_card = &self.cards[fd_card_index];
+        if fd_card.suit() != card.suit() {
+            return Err(MoveError::WrongSuit)
+        }
+        if !fd_card.rank().is_below(card.rank()) {
+            return Err(MoveError::WrongRank)
+        }
+        Ok(())
+    }
+
+    // turn over the last card if not empty, true when it was face down
+    pub fn turn_over_last(&mut self, tab_index: usize) -> bool {
+        match self.tab_card_indexes[tab_index].last() {
+            Some(&index) if !self.cards[index].visible => {
+                self.cards[index].visible = true;
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+/// Undo and redo stacks of whole positions, so automatic card flips and
+/// the round counters are restored along with the piles.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct History {
+    undo: Vec<Game>,
+    redo: Vec<Game>,
+}
+
+impl History {
+    /// Records the position before a move, a new move drops the redo stack.
+    pub fn record(&mut self, before: Game) {
+        self.undo.push(before);
+        self.redo.clear();
+    }
+
+    pub fn undo(&mut self, game: &mut Game) -> bool {
+        let Some(previous) = self.undo.pop() else {
+            return false
+        };
+        self.redo.push(std::mem::replace(game, previous));
+        true
+    }
+
+    pub fn redo(&mut self, game: &mut Game) -> bool {
+        let Some(next) = self.redo.pop() else {
+            return false
+        };
+        self.undo.push(std::mem::replace(game, next));
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+/// Shuffles the deck for a deal number.
+///
+/// The shuffle uses its own generator rather than `rand` so a deal number
+/// gives the same cards on every platform and with every `rand` version.
+pub fn load_cards(deal: u32) -> Vec<Card> {
+    shuffle(deck(), deal)
+}
+
+/// Shuffles any set of cards the same way for a deal number.
+pub fn shuffle(mut cards: Vec<Card>, deal: u32) -> Vec<Card> {
+    let mut rng = DealRng(deal as u64);
+
+    // Fisher-Yates
+    for i in (1..cards.len()).rev() {
+        let j = (rng.next() % (i as u64 + 1)) as usize;
+        cards.swap(i, j);
+    }
+
+    cards
+}
+
+// SplitMix64
+struct DealRng(u64);
+
+impl DealRng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[test]
+fn test_load_cards() {
+    let cards = load_cards(1);
+
+    // search for any duplicates
+    for (index, card) in cards.iter().enumerate() {
+        for other in cards[index+1..].iter() {
+            assert_ne!(other.id, card.id);
+        }
+    }
+}
+
+#[test]
+fn test_deal_is_reproducible() {
+    let first = Game::deal(VariantKind::Klondike, 1234, 3);
+    let second = Game::deal(VariantKind::Klondike, 1234, 3);
+    let other = Game::deal(VariantKind::Klondike, 1235, 3);
+
+    let values = |game: &Game| game.cards.iter().map(|card| card.id.index()).collect::<Vec<_>>();
+    assert_eq!(values(&first), values(&second));
+    assert_ne!(values(&first), values(&other));
+    assert_eq!(1234, first.deal);
+
+    // pinned so a change to the shuffle can not silently renumber every deal
+    assert_eq!(&values(&Game::deal(VariantKind::Klondike, 1, 3))[..5], &[19, 8, 16, 33, 20]);
+}
+
+#[test]
+fn test_move_cards_stock_to_waste() {
+    let mut game = Game::shuffled(VariantKind::Klondike, 3);
+
+    let stock_len = game.stock_card_indexes.len();
+    let waste_len = game.waste_card_indexes.len();
+
+    game.apply(Move::StockToWaste).unwrap();
+
+    let final_stock_len = game.stock_card_indexes.len();
+    let final_waste_len = game.waste_card_indexes.len();
+
+    assert_eq!(stock_len-3, final_stock_len);
+    assert_eq!(waste_len+3, final_waste_len);
+    assert_eq!(1, game.moves);
+}
+
+#[test]
+fn test_move_waste_to_stock() {
+    let mut game = Game::shuffled(VariantKind::Klondike, 3);
+
+    let stock_len = game.stock_card_indexes.len();
+
+    // remove one to have an even number
+    game.stock_card_indexes.remove(3);
+
+    while !game.stock_card_indexes.is_empty() {
+        game.apply(Move::StockToWaste).unwrap();
+    }
+    assert_eq!(game.apply(Move::StockToWaste), Err(MoveError::EmptySource));
+
+    game.apply(Move::Recycle).unwrap();
+
+    let final_stock_len = game.stock_card_indexes.len();
+    let final_waste_len = game.waste_card_indexes.len();
+
+    assert_eq!(stock_len-1, final_stock_len);
+    assert_eq!(0, final_waste_len);
+    assert_eq!(1, game.card_rounds);
+}
+
+#[test]
+fn test_move_waste_to_tab() {
+    let mut game = Game::shuffled(VariantKind::Klondike, 3);
+
+    // move 3 cards over to waste first
+    game.apply(Move::StockToWaste).unwrap();
+
+    let top_card = *game.waste_card_indexes.last().unwrap();
+
+    let tab_index = 4;
+    let before_last_tab_card = *game.tab_card_indexes[tab_index].last().unwrap();
+
+    // setup incoming card for value and color
+    game.cards[before_last_tab_card].id = CardId::new(Suit::Hearts, Rank::Five);
+    game.cards[top_card].id = CardId::new(Suit::Spades, Rank::Four);
+
+    assert_eq!(game.apply(Move::WasteToTableau(tab_index)), Ok(()));
+
+    let card = *game.tab_card_indexes[tab_index].last().unwrap();
+    let waste_card = *game.waste_card_indexes.last().unwrap();
+
+    assert_eq!(top_card, card);
+    assert_ne!(top_card, waste_card);
+    assert_eq!(1, game.cards_moved_from_waste);
+}
+
+#[test]
+fn test_move_waste_to_foundation() {
+    let mut game = Game::shuffled(VariantKind::Klondike, 3);
+
+    // move 3 cards over to waste first
+    game.apply(Move::StockToWaste).unwrap();
+
+    let top_card = *game.waste_card_indexes.last().unwrap();
+
+    // only an ace can start a foundation
+    game.cards[top_card].id = CardId::new(Suit::Clubs, Rank::Two);
+    assert_eq!(game.apply(Move::WasteToFoundation(0)), Err(MoveError::NotAceOnEmpty));
+    assert_eq!(None, game.foundation_cards[0]);
+
+    game.cards[top_card].id = CardId::new(Suit::Clubs, Rank::Ace);
+    assert_eq!(game.apply(Move::WasteToFoundation(0)), Ok(()));
+    assert_eq!(Some(top_card), game.foundation_cards[0]);
+}
+
+#[test]
+fn test_move_tab_to_tab() {
+    let mut game = Game::shuffled(VariantKind::Klondike, 3);
+
+    let tab_index_from = 3;
+    let tab_index_to = 4;
+
+    let before_len_from = game.tab_card_indexes[tab_index_from].len();
+    let before_len_to = game.tab_card_indexes[tab_index_to].len();
+
+    // setup the moving card one less and the opposite color
+    let from_card = *game.tab_card_indexes[tab_index_from].last().unwrap();
+    let to_card = *game.tab_card_indexes[tab_index_to].last().unwrap();
+    game.cards[to_card].id = CardId::new(Suit::Diamonds, Rank::Nine);
+    game.cards[from_card].id = CardId::new(Suit::Clubs, Rank::Eight);
+
+    assert_eq!(game.apply(Move::TableauToTableau { from: tab_index_from, depth: 1, to: tab_index_to }), Ok(()));
+
+    let after_len_from = game.tab_card_indexes[tab_index_from].len();
+    let after_len_to = game.tab_card_indexes[tab_index_to].len();
+
+    assert_eq!(before_len_from-1, after_len_from);
+    assert_eq!(before_len_to+1, after_len_to);
+
+    // the card underneath is turned over
+    let last = *game.tab_card_indexes[tab_index_from].last().unwrap();
+    assert!(game.cards[last].visible);
+}
+
+#[test]
+fn test_move_tab_to_foundation() {
+    let mut game = Game::new(VariantKind::Klondike, deck(), 3);
+
+    // the first tableau column holds the ace of clubs
+    assert_eq!(game.apply(Move::TableauToFoundation { from: 0, to: 2 }), Ok(()));
+
+    assert_eq!(Some(0), game.foundation_cards[2]);
+    assert!(game.tab_card_indexes[0].is_empty());
+}
+
+#[test]
+fn test_legal_moves() {
+    let game = Game::new(VariantKind::Klondike, deck(), 3);
+
+    // the unshuffled deal leaves the ace of clubs alone in the first column
+    let moves = game.legal_moves();
+    assert!(moves.contains(&Move::StockToWaste));
+    assert!(!moves.contains(&Move::Recycle));
+    for to in 0..4 {
+        assert!(moves.contains(&Move::TableauToFoundation { from: 0, to }));
+    }
+
+    for mv in moves {
+        let mut next = game.clone();
+        assert_eq!(next.apply(mv), Ok(()), "{:?}", mv);
+    }
+}
+
+#[test]
+fn test_apply_illegal_move() {
+    let mut game = Game::new(VariantKind::Klondike, deck(), 3);
+
+    // the waste is empty so nothing can come from it
+    assert_eq!(game.apply(Move::WasteToFoundation(0)), Err(MoveError::EmptySource));
+    assert_eq!(game.apply(Move::Recycle), Err(MoveError::StockNotEmpty));
+    // face down cards can not be moved
+    assert_eq!(game.apply(Move::TableauToTableau { from: 6, depth: 2, to: 0 }), Err(MoveError::FaceDown));
+    assert_eq!(game.apply(Move::TableauToTableau { from: 6, depth: 8, to: 0 }), Err(MoveError::BadDepth));
+    assert_eq!(game.apply(Move::TableauToTableau { from: 6, depth: 1, to: 6 }), Err(MoveError::SameColumn));
+    // the 3 of clubs can not go on the 6 of clubs
+    assert_eq!(game.apply(Move::TableauToTableau { from: 1, depth: 1, to: 2 }), Err(MoveError::WrongColor));
+    // only a king can go to the emptied first column
+    game.apply(Move::TableauToFoundation { from: 0, to: 0 }).unwrap();
+    assert_eq!(game.apply(Move::TableauToTableau { from: 1, depth: 1, to: 0 }), Err(MoveError::NotKingOnEmpty));
+    assert_eq!(game.apply(Move::TableauToFoundation { from: 3, to: 0 }), Err(MoveError::WrongRank));
+    assert_eq!(24, game.stock_card_indexes.len());
+}
+
+#[test]
+fn test_undo_redo() {
+    let mut game = Game::new(VariantKind::Klondike, deck(), 1);
+    let mut history = History::default();
+
+    // make the top of the second column the 2 of clubs so it can follow the ace
+    game.cards[2].id = CardId::new(Suit::Clubs, Rank::Two);
+
+    let moves = [
+        Move::StockToWaste,
+        Move::TableauToFoundation { from: 0, to: 0 },
+        Move::TableauToFoundation { from: 1, to: 0 },
+    ];
+    let mut positions = vec![game.clone()];
+    for mv in moves {
+        history.record(game.clone());
+        game.apply(mv).unwrap();
+        positions.push(game.clone());
+    }
+    // moving the top card off turned the card under it over
+    assert!(game.cards[1].visible);
+
+    while history.undo(&mut game) {}
+    assert!(!history.can_undo());
+    assert!(!game.cards[1].visible);
+    assert_eq!(positions[0].stock_card_indexes, game.stock_card_indexes);
+    assert_eq!(positions[0].foundation_cards, game.foundation_cards);
+
+    assert!(history.redo(&mut game));
+    assert_eq!(positions[1].waste_card_indexes, game.waste_card_indexes);
+
+    // a new move drops whatever was left to redo
+    history.record(game.clone());
+    game.apply(Move::StockToWaste).unwrap();
+    assert!(!history.can_redo());
+}
+
+#[test]
+fn test_undo_recycle() {
+    let mut game = Game::new(VariantKind::Klondike, deck(), 3);
+    let mut history = History::default();
+
+    while !game.stock_card_indexes.is_empty() {
+        history.record(game.clone());
+        game.apply(Move::StockToWaste).unwrap();
+    }
+    history.record(game.clone());
+    game.apply(Move::Recycle).unwrap();
+    assert_eq!(1, game.card_rounds);
+
+    history.undo(&mut game);
+    assert_eq!(0, game.card_rounds);
+    assert!(game.stock_card_indexes.is_empty());
+    assert_eq!(24, game.waste_card_indexes.len());
+}
+
+#[test]
+fn test_auto_finish() {
+    let mut game = Game::new(VariantKind::Klondike, deck(), 1);
+    assert!(!game.can_auto_finish());
+
+    // everything is up to the jacks, the queens and kings are left face up
+    // with the king of hearts buried under the queen of clubs
+    let index = |suit, rank| CardId::new(suit, rank).index();
+    game.stock_card_indexes.clear();
+    game.foundation_cards = Suit::ALL.iter().map(|suit| Some(index(*suit, Rank::Jack))).collect();
+    game.tab_card_indexes = vec![
+        vec![index(Suit::Hearts, Rank::King), index(Suit::Clubs, Rank::Queen)],
+        vec![index(Suit::Spades, Rank::King), index(Suit::Diamonds, Rank::Queen)],
+        vec![index(Suit::Clubs, Rank::King), index(Suit::Hearts, Rank::Queen)],
+        vec![index(Suit::Diamonds, Rank::King), index(Suit::Spades, Rank::Queen)],
+        vec![], vec![], vec![],
+    ];
+    for card in game.cards.iter_mut() {
+        card.visible = true;
+    }
+    assert!(game.can_auto_finish());
+
+    let mut moves = 0;
+    while let Some(mv) = game.finish_move() {
+        game.apply(mv).unwrap();
+        moves += 1;
+    }
+    assert_eq!(8, moves);
+    assert!(game.is_won());
+    assert!(!game.can_auto_finish());
+}
+
+#[test]
+fn test_foundation_to_tab() {
+    let mut game = Game::new(VariantKind::Klondike, deck(), 3);
+
+    // the ace and two of clubs go up, then the two comes back down on the
+    // red three made the top of the third column
+    game.cards[2].id = CardId::new(Suit::Clubs, Rank::Two);
+    game.cards[1].id = CardId::new(Suit::Clubs, Rank::Three);
+    game.cards[5].id = CardId::new(Suit::Hearts, Rank::Three);
+    game.apply(Move::TableauToFoundation { from: 0, to: 0 }).unwrap();
+    game.apply(Move::TableauToFoundation { from: 1, to: 0 }).unwrap();
+
+    assert!(game.legal_moves().contains(&Move::FoundationToTableau { from: 0, to: 2 }));
+    assert_eq!(game.apply(Move::FoundationToTableau { from: 0, to: 1 }), Err(MoveError::WrongColor));
+    assert_eq!(game.apply(Move::FoundationToTableau { from: 1, to: 2 }), Err(MoveError::EmptySource));
+    game.apply(Move::FoundationToTableau { from: 0, to: 2 }).unwrap();
+
+    assert_eq!(Some(0), game.foundation_cards[0]);
+    assert_eq!(Some(&2), game.tab_card_indexes[2].last());
+}
+
+#[test]
+fn test_passes() {
+    let mut game = Game::new(VariantKind::Klondike, deck(), 3).with_passes(Passes::Three);
+
+    for pass in 0..3 {
+        while !game.stock_card_indexes.is_empty() {
+            game.apply(Move::StockToWaste).unwrap();
+        }
+        if pass < 2 {
+            assert!(game.has_passes_left());
+            game.apply(Move::Recycle).unwrap();
+        }
+    }
+
+    assert_eq!(2, game.card_rounds);
+    assert!(!game.has_passes_left());
+    assert_eq!(game.apply(Move::Recycle), Err(MoveError::NoPassesLeft));
+    assert!(!game.legal_moves().contains(&Move::Recycle));
+
+    let game = Game::new(VariantKind::Klondike, deck(), 1).with_passes(Passes::One);
+    assert!(!game.has_passes_left());
+    assert!(Game::new(VariantKind::Klondike, deck(), 1).has_passes_left());
+}
+
+#[test]
+fn test_dead_position() {
+    let game = Game::new(VariantKind::Klondike, deck(), 3);
+    assert!(!game.is_dead());
+
+    // only the two of clubs is left out of the foundations, buried under the
+    // three in the one column with nothing in the stock to move the three
+    let index = |suit, rank| CardId::new(suit, rank).index();
+    let mut game = Game::new(VariantKind::Klondike, deck(), 3);
+    game.stock_card_indexes = vec![index(Suit::Clubs, Rank::King)];
+    game.foundation_cards = vec![Some(index(Suit::Clubs, Rank::Ace)), Some(index(Suit::Spades, Rank::King)),
+                                 Some(index(Suit::Hearts, Rank::King)), Some(index(Suit::Diamonds, Rank::King))];
+    game.tab_card_indexes = vec![vec![index(Suit::Clubs, Rank::Two), index(Suit::Clubs, Rank::Three)],
+                                 vec![], vec![], vec![], vec![], vec![], vec![]];
+    game.cards[index(Suit::Clubs, Rank::Three)].visible = true;
+
+    // the king can still come out of the stock onto an empty column
+    assert!(!game.is_dead());
+    game.stock_card_indexes = vec![index(Suit::Clubs, Rank::Four)];
+    assert!(game.is_dead());
+
+    // either black six comes down onto the seven of hearts so that the five
+    // of hearts can go onto it and turn the four over
+    let mut game = Game::new(VariantKind::Klondike, deck(), 3);
+    game.stock_card_indexes.clear();
+    game.foundation_cards = vec![Some(index(Suit::Spades, Rank::Six)), Some(index(Suit::Clubs, Rank::Six)),
+                                 Some(index(Suit::Hearts, Rank::Three)), Some(index(Suit::Diamonds, Rank::Six))];
+    game.tab_card_indexes = vec![vec![index(Suit::Hearts, Rank::Four), index(Suit::Hearts, Rank::Five)],
+                                 vec![index(Suit::Hearts, Rank::Seven)], vec![], vec![], vec![], vec![], vec![]];
+    game.cards[index(Suit::Hearts, Rank::Five)].visible = true;
+    game.cards[index(Suit::Hearts, Rank::Seven)].visible = true;
+
+    assert!(!game.is_dead());
+    let down = |from| Move::FoundationToTableau { from, to: 1 };
+    assert_eq!(vec![down(0), down(1)], game.productive_moves());
+}
@@ -1,87 +1,570 @@
 //! Solitaire
-use iced::widget::{button, column, container, row, vertical_space, text, pick_list};
+use std::time::Duration;
+
+use iced::keyboard::{self, key::Named, Key};
+use iced::widget::{button, center, checkbox, column, container, opaque, row, scrollable, stack, vertical_space, text, text_input, pick_list};
 use iced::Length::Fill;
-use iced::{Element, Length, Padding, Size, Theme};
+use iced::{event, time, window, Element, Event, Length, Padding, Point, Size, Subscription, Task, Theme};
+
+mod card;
+mod clock;
+mod game;
+mod save;
+mod score;
+mod solver;
+mod stats;
+mod variant;
+
+use game::Passes;
+use save::SavedGame;
+use score::Scoring;
+use stats::{RecordKey, Statistics};
+use variant::VariantKind;
 
 pub fn main() -> iced::Result {
     iced::application("Solitaire", Solitaire::update, Solitaire::view)
+        .subscription(Solitaire::subscription)
         .theme(|_| Theme::CatppuccinMocha)
         .antialiasing(true)
         .centered()
         .window_size(Size{ width: 1100.0, height: 800.0 })
+        // the game is saved before the window closes
+        .exit_on_close_request(false)
         .run()
 }
 
-#[derive(Default)]
 struct Solitaire {
     board: board::State,
+    status: String,
+    deal_input: String,
+    // game left from the last session, offered until a game is started
+    saved: Option<SavedGame>,
+    // adds Vegas results to the bankroll kept with the statistics
+    keep_bankroll: bool,
+    // the clock only runs while the window has focus
+    focused: bool,
+    statistics: Statistics,
+    show_statistics: bool,
+    // the game on the board has gone into the statistics
+    recorded: bool,
+}
+
+impl Default for Solitaire {
+    fn default() -> Self {
+        let (saved, mut status) = match save::load() {
+            Ok(saved) => (saved, String::new()),
+            Err(error) => (None, error.to_string()),
+        };
+        let statistics = Statistics::load().unwrap_or_else(|error| {
+            status = error.to_string();
+            Statistics::default()
+        });
+
+        Solitaire {
+            board: board::State::default(),
+            status,
+            deal_input: String::new(),
+            saved,
+            keep_bankroll: false,
+            focused: true,
+            statistics,
+            show_statistics: false,
+            recorded: false,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 enum Message {
     MoveCard(board::CardPosition),
+    ToFoundation(Point),
     Start,
+    Restart,
+    DealInput(String),
+    PlayDeal,
+    Undo,
+    Redo,
+    Resume,
+    Save,
+    Load,
+    CloseRequested(window::Id),
     SelectCardsToPlay(String),
+    SelectScoring(Scoring),
+    SelectPasses(Passes),
+    SelectVariant(VariantKind),
+    KeepBankroll(bool),
+    ToggleDrag(bool),
+    Key(board::Key),
+    Tick,
+    ShowStatistics(bool),
+    ResetStatistics,
+    UndoToChoice,
+    Pause,
+    Focused(bool),
+    Draw,
+    Solve,
+    Solved(u64, solver::Solution),
+    Hint,
+    AutoFinish,
 }
 
 impl Solitaire {
-    fn update(&mut self, message: Message) {
+    fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::MoveCard(positions) => {
-                self.board.position = positions.clone();
-                let areas = self.board.move_cards(positions);
-                self.board.recalc_tab_positions();
-                self.board.request_redraw(areas);
+                self.board.position = positions;
+                let moved = self.board.move_cards(positions);
+                self.moved(moved);
+            },
+            Message::ToFoundation(point) => {
+                let moved = self.board.send_to_foundation(point);
+                self.moved(moved);
             },
             Message::Start => {
-                self.board.start();
-                for i in 0..7 {
-                    self.board.tab_cache[i].clear();
+                self.new_game(None);
+            },
+            Message::Restart => {
+                self.new_game(Some(self.board.game.deal));
+            },
+            Message::DealInput(input) => {
+                self.deal_input = input;
+            },
+            Message::PlayDeal => {
+                if let Ok(deal) = self.deal_input.trim().parse() {
+                    self.new_game(Some(deal));
                 }
-                self.board.waste_cache.clear();
-                self.board.stock_cache.clear();
-                self.board.foundation_cache.clear();
+            },
+            Message::Undo => {
+                self.status.clear();
+                self.board.undo();
+            },
+            Message::Redo => {
+                self.status.clear();
+                self.board.redo();
+            },
+            Message::Resume => {
+                if let Some(saved) = self.saved.take() {
+                    self.abandon();
+                    self.status.clear();
+                    self.board.resume(saved.game, saved.history, saved.clock);
+                    self.recorded = false;
+                }
+            },
+            Message::Save => {
+                self.status = match save::save(&self.board.game, &self.board.history, self.board.clock) {
+                    Ok(()) => "Game saved".to_string(),
+                    Err(error) => error.to_string(),
+                };
+            },
+            Message::Load => {
+                self.status = match save::load() {
+                    Ok(Some(saved)) => {
+                        self.abandon();
+                        self.saved = None;
+                        self.board.resume(saved.game, saved.history, saved.clock);
+                        self.recorded = false;
+                        "Game loaded".to_string()
+                    },
+                    Ok(None) => "There is no saved game".to_string(),
+                    Err(error) => error.to_string(),
+                };
+            },
+            Message::CloseRequested(id) => {
+                // a won game is in the statistics already and is not played on
+                if self.board.start && !self.board.game.is_won() {
+                    if let Err(error) = save::save(&self.board.game, &self.board.history, self.board.clock) {
+                        eprintln!("{}", error);
+                    }
+                }
+                return window::close(id)
             },
             Message::SelectCardsToPlay(selected) => {
+                // takes effect on the next deal, like the scoring
                 self.board.cards_to_play = Some(selected);
-            }
+            },
+            Message::Key(key) => {
+                if self.board.start {
+                    if let Some(positions) = self.board.key(key) {
+                        let moved = self.board.move_cards(positions);
+                        self.moved(moved);
+                    }
+                }
+            },
+            Message::UndoToChoice => {
+                self.status.clear();
+                self.board.undo_to_choice();
+            },
+            Message::ShowStatistics(show) => {
+                self.show_statistics = show;
+            },
+            Message::ResetStatistics => {
+                self.statistics = Statistics::default();
+                self.save_statistics();
+            },
+            Message::Tick => {
+                self.board.clock.tick(Duration::from_secs(1));
+            },
+            Message::Pause => {
+                let paused = self.board.clock.is_paused();
+                self.board.clock.set_paused(!paused);
+            },
+            Message::Focused(focused) => {
+                self.focused = focused;
+            },
+            Message::Draw => {
+                if self.board.start {
+                    let moved = self.board.play(self.board.stock_move());
+                    self.moved(moved);
+                }
+            },
+            Message::SelectScoring(scoring) => {
+                self.board.scoring = scoring;
+            },
+            Message::SelectPasses(passes) => {
+                self.board.passes = passes;
+            },
+            Message::SelectVariant(variant) => {
+                self.board.variant = variant;
+            },
+            Message::KeepBankroll(keep) => {
+                self.keep_bankroll = keep;
+            },
+            Message::ToggleDrag(drag) => {
+                self.board.drag = drag;
+            },
+            Message::Solve => {
+                self.status = "Solving...".to_string();
+                let game = self.board.game.clone();
+                let key = solver::key(&game);
+                // the search keeps a core busy for seconds, so it runs off the executor
+                let search = tokio::task::spawn_blocking(move || solver::solve(&game, solver::Budget::default()));
+                return Task::perform(async move { search.await.unwrap_or(solver::Solution::Unknown) },
+                                     move |solution| Message::Solved(key, solution))
+            },
+            Message::Solved(key, solution) => {
+                // a move was played while searching, the answer is for another position
+                if key != solver::key(&self.board.game) {
+                    return Task::none()
+                }
+                self.status = match solution {
+                    solver::Solution::Winnable(moves) => format!("Winnable in {} moves", moves.len()),
+                    solver::Solution::Unwinnable => "This game can not be won from here".to_string(),
+                    solver::Solution::Unknown => "The solver gave up".to_string(),
+                };
+            },
+            Message::AutoFinish => {
+                let areas = self.board.auto_finish();
+                self.moved(Ok(areas));
+            },
+            Message::Hint => {
+                self.status = match self.board.next_hint() {
+                    Some((index, count)) => format!("Hint {} of {}", index + 1, count),
+                    None => "There are no moves left".to_string(),
+                };
+            },
+        }
+
+        Task::none()
+    }
+
+    // shown over the board once the game is won, the board under it takes no clicks
+    fn summary(&self) -> Element<'_, Message> {
+        let game = &self.board.game;
+
+        let summary = column![
+            text("You won!").size(32),
+            text(format!("Deal #{}", game.deal)),
+            text(format!("Moves: {}", game.moves)),
+            text(format!("Time: {}", self.board.clock.format())),
+            text(format!("Score: {}", game.scoring.format(game.score))),
+            text(format!("Passes: {}", game.card_rounds + 1)),
+            row![
+                button("New deal").on_press(Message::Start),
+                button("Replay deal").on_press(Message::Restart),
+            ].spacing(10.0),
+        ]
+        .spacing(10.0);
+
+        opaque(center(container(summary).padding(30.0).style(container::rounded_box)))
+    }
+
+    // the records of the game picked in the sidebar, one column per draw mode
+    fn statistics_panel(&self) -> Element<'_, Message> {
+        let variant = self.board.variant;
+        let columns = if variant.rules().layout().waste {
+            vec![("Draw 1", RecordKey::new(variant, 1)), ("Draw 3", RecordKey::new(variant, 3))]
+        } else {
+            vec![("", RecordKey::new(variant, 1))]
+        };
+
+        let line = |label: &str, value: &dyn Fn(&stats::Record) -> String| -> Element<'_, Message> {
+            let values = columns.iter()
+                .map(|(_, key)| text(value(self.statistics.record(*key))).width(Length::Fixed(80.0)).into());
+            row(std::iter::once(text(label.to_string()).width(Length::Fixed(130.0)).into()).chain(values)).into()
+        };
+        let or_none = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+
+        let headings = columns.iter().map(|(heading, _)| text(*heading).width(Length::Fixed(80.0)).into());
+
+        let panel = column![
+            text("Statistics").size(32),
+            text(variant.to_string()),
+            row(std::iter::once(text("").width(Length::Fixed(130.0)).into()).chain(headings)),
+            line("Played", &|record| record.played.to_string()),
+            line("Won", &|record| record.won.to_string()),
+            line("Win percentage", &|record| format!("{:.0}%", record.win_percentage())),
+            line("Current streak", &|record| record.streak.to_string()),
+            line("Longest streak", &|record| record.longest_streak.to_string()),
+            line("Best time", &|record| or_none(record.best_time.map(|time| {
+                format!("{:02}:{:02}", time.as_secs() / 60, time.as_secs() % 60)
+            }))),
+            line("Fewest moves", &|record| or_none(record.fewest_moves.map(|moves| moves.to_string()))),
+            line("Best score", &|record| or_none(record.best_score.map(|score| score.to_string()))),
+            line("Best Vegas", &|record| or_none(record.best_vegas.map(|score| Scoring::Vegas.format(score)))),
+            row![
+                button("Reset").on_press(Message::ResetStatistics),
+                button("Close").on_press(Message::ShowStatistics(false)),
+            ].spacing(10.0),
+        ]
+        .spacing(10.0);
+
+        opaque(center(container(panel).padding(30.0).style(container::rounded_box)))
+    }
+
+    fn new_game(&mut self, deal: Option<u32>) {
+        // the Vegas result of the game being left goes into the bankroll
+        if self.keep_bankroll && self.board.start && self.board.game.scoring == Scoring::Vegas {
+            self.statistics.bankroll += self.board.game.score;
+            self.save_statistics();
+        }
+        self.abandon();
+        self.recorded = false;
+        self.status.clear();
+        self.board.start(deal);
+        self.board.redraw_all();
+    }
+
+    // a game given up on after it was started counts as lost, a won one
+    // that came back from a save was counted when it was won
+    fn abandon(&mut self) {
+        let game = &self.board.game;
+        if self.board.start && !self.recorded && game.moves > 0 && !game.is_won() {
+            self.statistics.record_mut(RecordKey::new(game.variant, game.cards_to_play)).loss();
+            self.save_statistics();
         }
     }
 
-    fn view(&self) -> Element<Message> {
+    fn save_statistics(&mut self) {
+        if let Err(error) = self.statistics.save() {
+            self.status = error.to_string();
+        }
+    }
+
+    fn moved(&mut self, moved: Result<Vec<board::Area>, game::MoveError>) {
+        match moved {
+            Ok(areas) => {
+                self.status.clear();
+                self.board.recalc_tab_positions();
+                self.board.request_redraw(areas);
+
+                if self.board.dead {
+                    self.status = "No moves are left, this game is lost".to_string();
+                }
+                if self.board.game.is_won() {
+                    self.board.clock.stop();
+                    if !self.recorded {
+                        let game = &self.board.game;
+                        self.statistics.record_mut(RecordKey::new(game.variant, game.cards_to_play))
+                            .win(self.board.clock.elapsed(), game.moves, game.scoring, game.score);
+                        self.recorded = true;
+                        self.save_statistics();
+                        // a save of this game would let it be played again
+                        if let Err(error) = save::remove() {
+                            self.status = error.to_string();
+                        }
+                    }
+                } else if self.board.game.moves > 0 {
+                    self.board.clock.start();
+                }
+            },
+            Err(error) => self.status = error.to_string(),
+        }
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let clock = if self.board.start && self.focused && self.board.clock.is_ticking() {
+            time::every(Duration::from_secs(1)).map(|_| Message::Tick)
+        } else {
+            Subscription::none()
+        };
+
+        Subscription::batch([
+            window::close_requests().map(Message::CloseRequested),
+            keyboard::on_key_press(key_message),
+            event::listen_with(focus_message),
+            clock,
+        ])
+    }
+
+    fn view(&self) -> Element<'_, Message> {
         let mut start_label = "Play";
         if self.board.start {
             start_label = "Play Again";
         }
         let btn_start: Element<Message> = button(start_label).on_press(Message::Start).into();
 
+        // the game picked is dealt by the next Play
+        let variant: Element<Message> = pick_list(VariantKind::ALL, Some(self.board.variant), Message::SelectVariant)
+                                            .into();
+
+        let btn_restart: Element<Message> = button("Restart this deal")
+                                            .on_press_maybe(self.board.start.then_some(Message::Restart))
+                                            .into();
+
+        let deal_str: Element<Message> = if self.board.start {
+            text(format!("Deal #{}", self.board.game.deal)).into()
+        } else {
+            text("").into()
+        };
+
+        let deal_number = self.deal_input.trim().parse::<u32>().ok();
+        let deal_input: Element<Message> = text_input("Deal number", &self.deal_input)
+                                            .on_input(Message::DealInput)
+                                            .on_submit_maybe(deal_number.map(|_| Message::PlayDeal))
+                                            .into();
+        let btn_play_deal: Element<Message> = button("Play Deal")
+                                            .on_press_maybe(deal_number.map(|_| Message::PlayDeal))
+                                            .into();
+
+        let btn_undo: Element<Message> = button("Undo")
+                                            .on_press_maybe(self.board.history.can_undo().then_some(Message::Undo))
+                                            .into();
+        let btn_redo: Element<Message> = button("Redo")
+                                            .on_press_maybe(self.board.history.can_redo().then_some(Message::Redo))
+                                            .into();
+        let undo_redo: Element<Message> = row(vec![btn_undo, btn_redo]).spacing(5.0).into();
+
+        // only offered once the game is lost
+        let btn_undo_to_choice: Element<Message> = if self.board.start && self.board.dead {
+            button("Undo to last choice")
+                .on_press_maybe(self.board.history.can_undo().then_some(Message::UndoToChoice))
+                .into()
+        } else {
+            vertical_space().height(0.0).into()
+        };
+
+        let btn_save: Element<Message> = button("Save")
+                                            .on_press_maybe((self.board.start && !self.board.game.is_won())
+                                                                .then_some(Message::Save))
+                                            .into();
+        let btn_load: Element<Message> = button("Load").on_press(Message::Load).into();
+        let save_load: Element<Message> = row(vec![btn_save, btn_load]).spacing(5.0).into();
+
+        // only offered until another game is started
+        let btn_resume: Element<Message> = if self.saved.is_some() && !self.board.start {
+            button("Resume saved game").on_press(Message::Resume).into()
+        } else {
+            vertical_space().height(0.0).into()
+        };
+
         let rounds_str: Element<Message> = text("Card Play Rounds:").into();
-        let rounds_value: Element<Message> = text(format!("{}", self.board.card_rounds))
+        let rounds_value: Element<Message> = text(format!("{}", self.board.game.card_rounds))
                                             .width(Fill)
                                             .into();
 
         let moved_from_waste_str: Element<Message> = text("Moved from Waste: ").into();
-        let moved_from_waste_value: Element<Message> = text(format!("{}", self.board.cards_moved_from_waste))
+        let moved_from_waste_value: Element<Message> = text(format!("{}", self.board.game.cards_moved_from_waste))
                                                         .width(Fill)
                                                         .into();
 
+        let game = &self.board.game;
+        let score_str: Element<Message> = text(format!("Score: {}", game.scoring.format(game.score))).into();
+        let bankroll_str: Element<Message> = if self.keep_bankroll && game.scoring == Scoring::Vegas {
+            text(format!("Bankroll: {}", Scoring::Vegas.format(self.statistics.bankroll + game.score))).into()
+        } else {
+            text("").into()
+        };
+
+        let clock = &self.board.clock;
+        let time_str: Element<Message> = text(format!("Time: {}", clock.format())).into();
+        let moves_str: Element<Message> = text(format!("Moves: {}", game.moves)).into();
+        let btn_pause: Element<Message> = button(if clock.is_paused() { "Continue" } else { "Pause" })
+                                            .on_press_maybe(clock.is_running().then_some(Message::Pause))
+                                            .into();
+
+        let scoring: Element<Message> = pick_list(Scoring::ALL, Some(self.board.scoring), Message::SelectScoring)
+                                            .into();
+        let keep_bankroll: Element<Message> = checkbox("Vegas bankroll", self.keep_bankroll)
+                                            .on_toggle(Message::KeepBankroll)
+                                            .into();
+
+        let passes_text: Element<Message> = text("Passes").into();
+        let passes: Element<Message> = pick_list(Passes::ALL, Some(self.board.passes), Message::SelectPasses)
+                                            .into();
+
         let to_play_text: Element<Message> = text("Cards to Play").into();
-        let cards_to_play: Element<Message> = pick_list(vec!["3".to_string(), "1".to_string()], 
+        let cards_to_play: Element<Message> = pick_list(vec!["3".to_string(), "1".to_string()],
                                                 self.board.cards_to_play.clone(),
                                                 Message::SelectCardsToPlay)
                                                 .into();
 
+        let btn_solve: Element<Message> = button("Solve")
+                                            .on_press_maybe(self.board.start.then_some(Message::Solve))
+                                            .into();
+
+        let drag: Element<Message> = checkbox("Drag and drop", self.board.drag)
+                                            .on_toggle(Message::ToggleDrag)
+                                            .into();
+
+        let btn_hint: Element<Message> = button("Hint")
+                                            .on_press_maybe(self.board.start.then_some(Message::Hint))
+                                            .into();
+        let hint_solve: Element<Message> = row(vec![btn_hint, btn_solve]).spacing(5.0).into();
+
+        let btn_statistics: Element<Message> = button("Statistics")
+                                            .on_press(Message::ShowStatistics(!self.show_statistics))
+                                            .into();
+
+        let btn_auto_finish: Element<Message> = button("Auto-finish")
+                                            .on_press_maybe(self.board.game.can_auto_finish().then_some(Message::AutoFinish))
+                                            .into();
+
+        let status: Element<Message> = text(&self.status).into();
+
         let instruction_space: Element<Message> = vertical_space().height(75.0).into();
 
-        let instructions: Element<Message> = text("Instructions:\nCards are moved by selecting source and destination using mouse.  If a card fails to move it means the validation failed, wrong color or value.\nTo cancel a move, click any other place on the canvas").into();        
-        
-        let col: Element<Message> = column(vec![btn_start, 
+        let instructions: Element<Message> = text("Instructions:\nCards are dragged to where they go, or moved by clicking the source and then the destination.  If a card fails to move the reason is shown above.\nTo cancel a move, click any other place on the canvas.\nDouble-click or right-click a card to send it to its foundation.\nKeys: arrows or the column numbers to move around, Enter picks up and drops, D draws, N deals, Ctrl+Z undoes").into();
+
+        let col: Element<Message> = column(vec![variant,
+                                                            btn_start,
+                                                            btn_resume,
+                                                            btn_restart,
+                                                            deal_str,
+                                                            deal_input,
+                                                            btn_play_deal,
+                                                            undo_redo,
+                                                            btn_undo_to_choice,
+                                                            save_load,
                                                             rounds_str,
-                                                            rounds_value, 
+                                                            rounds_value,
                                                             moved_from_waste_str,
                                                             moved_from_waste_value,
                                                             to_play_text,
                                                             cards_to_play,
+                                                            passes_text,
+                                                            passes,
+                                                            scoring,
+                                                            keep_bankroll,
+                                                            score_str,
+                                                            bankroll_str,
+                                                            time_str,
+                                                            moves_str,
+                                                            btn_pause,
+                                                            drag,
+                                                            hint_solve,
+                                                            btn_auto_finish,
+                                                            btn_statistics,
+                                                            status,
                                                             instruction_space,
                                                             instructions,
                                                             ])
@@ -90,28 +573,86 @@ impl Solitaire {
                                             .padding(Padding{ top: 20.0, right: 0.0, bottom: 0.0, left: 20.0 })
                                             .into();
 
-        let cont = container(
-            self.board.view().map(Message::MoveCard)
-        )
+        let canvas: Element<Message> = self.board.view().map(|action| match action {
+                board::Action::Move(positions) => Message::MoveCard(positions),
+                board::Action::ToFoundation(point) => Message::ToFoundation(point),
+            });
+        let board: Element<Message> = if self.show_statistics {
+            stack![canvas, self.statistics_panel()].into()
+        } else if self.board.start && self.board.game.is_won() {
+            stack![canvas, self.summary()].into()
+        } else {
+            canvas
+        };
+
+        let cont = container(board)
         .padding(Padding{ top: 20.0, right: 20.0, bottom: 20.0, left: 0.0 })
         .into();
 
-        row(vec![col, cont]).into()
+        // the sidebar scrolls when the window is too short for it
+        let sidebar: Element<Message> = scrollable(col).width(Length::Fixed(160.0)).into();
+
+        row(vec![sidebar, cont]).into()
 
     }
 }
 
+fn focus_message(event: Event, _status: event::Status, _id: window::Id) -> Option<Message> {
+    match event {
+        Event::Window(window::Event::Focused) => Some(Message::Focused(true)),
+        Event::Window(window::Event::Unfocused) => Some(Message::Focused(false)),
+        _ => None,
+    }
+}
+
+// keys typed into the deal number box never get here
+fn key_message(key: Key, modifiers: keyboard::Modifiers) -> Option<Message> {
+    let message = match key.as_ref() {
+        Key::Named(Named::ArrowLeft) => Message::Key(board::Key::Left),
+        Key::Named(Named::ArrowRight) => Message::Key(board::Key::Right),
+        Key::Named(Named::ArrowUp) => Message::Key(board::Key::Up),
+        Key::Named(Named::ArrowDown) => Message::Key(board::Key::Down),
+        Key::Named(Named::Enter | Named::Space) => Message::Key(board::Key::Select),
+        Key::Named(Named::Escape) => Message::Key(board::Key::Cancel),
+        // with Shift held the key comes as a capital letter
+        Key::Character(letter) if modifiers.command() && letter.eq_ignore_ascii_case("z") => {
+            if modifiers.shift() { Message::Redo } else { Message::Undo }
+        },
+        Key::Character(letter) if modifiers.command() && letter.eq_ignore_ascii_case("y") => Message::Redo,
+        // other shortcuts with a modifier are left alone
+        _ if modifiers.command() || modifiers.control() || modifiers.alt() || modifiers.logo() => return None,
+        Key::Character("d") => Message::Draw,
+        Key::Character("n") => Message::Start,
+        Key::Character(number) => {
+            // 0 is the tenth column
+            let column: usize = number.parse().ok().filter(|column| (0..=9).contains(column))?;
+            Message::Key(board::Key::Column((column + 9) % 10))
+        },
+        _ => return None,
+    };
+    Some(message)
+}
+
 mod board {
+    use std::time::{Duration, Instant};
+
     use iced::advanced::image::Handle;
     use iced::{mouse, Color};
     use iced::widget::canvas::event::{self, Event};
     use iced::widget::canvas::{self, Canvas, Frame, Geometry, Path, Stroke, Style};
     use iced::{Element, Fill, Point, Rectangle, Renderer, Theme};
 
-    use rand::seq::SliceRandom;
-    use rand::thread_rng;
+    use crate::card::{Rank, Suit};
+    #[cfg(test)]
+    use crate::card::CardId;
+    use crate::clock::Clock;
+    use crate::game::{Game, History, Move, MoveError, Passes};
+    use crate::score::Scoring;
+    use crate::solver;
+    use crate::variant::{row_and_place, Layout, Shape, VariantKind};
+    #[cfg(test)]
+    use crate::variant::Suits;
 
-    #[derive()]
     pub struct State {
         pub start: bool,
 
@@ -119,37 +660,52 @@ mod board {
         pub stock_cache: canvas::Cache,
         pub table_cache: canvas::Cache,
         pub waste_cache: canvas::Cache,
+        pub cell_cache: canvas::Cache,
         pub tab_cache: Vec<canvas::Cache>,
 
         pub position: CardPosition,
 
+        pub game: Game,
+        pub history: History,
+        // the piles of the game on the board
+        layout: Layout,
+        // suggested move and its place among the alternatives
+        hint: Option<(usize, Move)>,
+        /// No productive move is left, the game is lost.
+        pub dead: bool,
+
         cover_image: Handle,
-        cards: Vec<Card>,
+        card_images: Vec<Handle>,
 
-        tab_card_indexes: Vec<Vec<usize>>,
         tab_positions: Vec<Vec<(Point, Point)>>,
-        
+
         tab_x_offset_pos: f32,
         tab_y_offset_pos: f32,
         card_dist_x: f32,
         card_dist_y: f32,
         card_size_x: f32,
         card_size_y: f32,
-        
-        foundation_cards: Vec<usize>,
+
         foundation_positions: Vec<(Point, Point)>,
-        
+        cell_positions: Vec<(Point, Point)>,
         stock_position: (Point, Point),
-        stock_card_indexes: Vec<usize>,
-        stock_area_image_index: Option<usize>,
-
         waste_position: (Point, Point),
-        waste_card_indexes: Vec<usize>,
-        waste_area_image_index: Option<usize>,
 
-        pub card_rounds: u16,
-        pub cards_moved_from_waste: u16,
+        /// Game dealt by the next start.
+        pub variant: VariantKind,
         pub cards_to_play: Option<String>,
+        /// Passes through the stock for the next deal.
+        pub passes: Passes,
+        pub clock: Clock,
+        /// Scoring for the next deal, a game keeps the scoring it was dealt with.
+        pub scoring: Scoring,
+        /// Cards follow the mouse while the button is held, a click on the
+        /// source and then the destination still works either way.
+        pub drag: bool,
+
+        // where the keyboard is pointing and the cards it picked up
+        focus: Option<Focus>,
+        held: Option<Point>,
     }
 
     impl State {
@@ -157,62 +713,89 @@ mod board {
             let path = format!("{}/assets/cards/card_back.png",
                 env!("CARGO_MANIFEST_DIR"));
 
-            let card_dist_x = 120.0;
-            let card_dist_y = 25.0;
-            
-            // init the foundation positions
-            let mut foundation_positions = vec![];
-            for i in 0..4 {
-                foundation_positions.push((Point { x: 400.0 + card_dist_x * i as f32, y: 25.0},
-                                    Point { x: 400.0 + card_dist_x * i as f32 + 100.0, y: 175.0}));
-            }
-
-            let mut tab_cache = vec![];
-            for _ in 0..7 {
-                tab_cache.push(canvas::Cache::default());
-            }
+            let game = Game::default();
+            let layout = game.variant.rules().layout();
 
-            State {
+            let mut state = State {
                 start: false,
 
                 foundation_cache: canvas::Cache::default(),
                 table_cache: canvas::Cache::default(),
                 stock_cache: canvas::Cache::default(),
                 waste_cache: canvas::Cache::default(),
-                tab_cache,
+                cell_cache: canvas::Cache::default(),
+                tab_cache: vec![],
                 position: CardPosition { from: Point::ORIGIN, to: Point::ORIGIN },
-            
+
+                game,
+                history: History::default(),
+                layout,
+                hint: None,
+                dead: false,
+
                 cover_image: Handle::from_path(path),
-                cards: vec![],
-                
-                tab_card_indexes: vec![],
+                card_images: load_card_images(),
+
                 tab_positions: vec![],
 
                 tab_x_offset_pos: 50.0,
-                tab_y_offset_pos: 250.0,
-                card_dist_x,
-                card_dist_y,
-                card_size_x: 100.0,
-                card_size_y: 150.0,
+                tab_y_offset_pos: 0.0,
+                card_dist_x: 0.0,
+                card_dist_y: 25.0,
+                card_size_x: 0.0,
+                card_size_y: 0.0,
+
+                foundation_positions: vec![],
+                cell_positions: vec![],
+                stock_position: (Point::ORIGIN, Point::ORIGIN),
+                waste_position: (Point::ORIGIN, Point::ORIGIN),
+
+                variant: VariantKind::default(),
+                cards_to_play: Some("3".to_string()),
+                scoring: Scoring::default(),
+                passes: Passes::default(),
+                clock: Clock::default(),
+                drag: true,
 
-                foundation_cards: vec![100, 100, 100, 100],
-                foundation_positions,
+                focus: None,
+                held: None,
+            };
+            state.lay_out();
+            state
+        }
 
-                stock_card_indexes: vec![],
-                stock_position: (Point{ x: 50.0, y: 25.0}, Point{ x: 150.0, y: 175.0 }),
-                stock_area_image_index: Some(100),
+        // places the piles of the game's variant, the cards shrink so the
+        // columns, or the bottom row of a pyramid, fit across the board
+        fn lay_out(&mut self) {
+            self.layout = self.game.variant.rules().layout();
+            let across = match self.layout.shape {
+                Shape::Columns => self.layout.columns,
+                Shape::Pyramid { rows } => rows,
+            };
 
-                waste_position: (Point{ x: 170.0, y: 25.0}, Point{ x: 270.0, y: 175.0 }),
-                waste_card_indexes: vec![],
-                waste_area_image_index: None,
+            self.card_dist_x = (BOARD_WIDTH / across as f32).min(120.0);
+            self.card_size_x = self.card_dist_x - 20.0;
+            self.card_size_y = self.card_size_x * 1.5;
+            self.card_dist_y = self.card_size_y / 6.0;
+            self.tab_y_offset_pos = 25.0 + self.card_size_y + 75.0;
 
-                card_rounds: 0,
-                cards_moved_from_waste: 0,
-                cards_to_play: Some("3".to_string()),
-            }
+            // the stock, waste and free cells on the left, the foundations to the right
+            let slot = |i: usize| {
+                let x = self.tab_x_offset_pos + self.card_dist_x * i as f32;
+                (Point { x, y: 25.0 }, Point { x: x + self.card_size_x, y: 25.0 + self.card_size_y })
+            };
+            self.stock_position = slot(0);
+            self.waste_position = slot(1);
+            let first = self.layout.stock as usize + self.layout.waste as usize;
+            self.cell_positions = (0..self.layout.cells).map(|i| slot(first + i)).collect();
+            let first = across.saturating_sub(self.layout.foundations);
+            self.foundation_positions = (0..self.layout.foundations).map(|i| slot(first + i)).collect();
+
+            self.tab_cache = (0..self.layout.columns).map(|_| canvas::Cache::default()).collect();
+            self.table_cache.clear();
         }
 
-        pub fn view<'a>(&'a self) -> Element<'a, CardPosition> {
+        pub fn view(&self) -> Element<'_, Action> {
             Canvas::new(CardsDraw {
                 state: self,
             })
@@ -234,457 +817,604 @@ mod board {
                     Area::Waste => {
                         self.waste_cache.clear()
                     },
+                    Area::Cell(_) => {
+                        self.cell_cache.clear()
+                    },
                     Area::Tableau(tab) => {
                         self.tab_cache[tab].clear()
                     },
                 }
             }
-            
-            
         }
-    }
 
-    
-    impl State {
-        pub fn start(&mut self) {
-
-            self.cards = load_cards();
-            let tableau = [1, 2, 3, 4, 5, 6, 7];
-            self.tab_positions = vec![vec![]; 7];
-            self.tab_card_indexes = vec![vec![]; 7];
-            self.foundation_cards = vec![100; 4];
-            self.stock_card_indexes = vec![];
-            self.waste_card_indexes = vec![];
-            self.waste_area_image_index = None;
-            self.card_rounds = 0;
-            self.cards_moved_from_waste = 0;
-
-            let mut card_index = 0;
-            for (i, tab_col) in tableau.iter().enumerate() {
-                for j in 0..*tab_col {
-                    if j == *tab_col-1 {
-                        self.tab_card_indexes[i].push(card_index);
-                        self.tab_positions[i].push((Point { x: self.tab_x_offset_pos + self.card_dist_x * i as f32, 
-                                                            y: self.tab_y_offset_pos + self.card_dist_y * j as f32},
-                                                    Point { x: self.tab_x_offset_pos + self.card_dist_x * i as f32 + self.card_size_x, 
-                                                            y: self.tab_y_offset_pos + self.card_dist_y + self.card_dist_y * j as f32 + self.card_size_y}));
-                        self.cards[card_index].visible = true;
-                    } else {
-                        self.tab_card_indexes[i].push(card_index);
-                        self.tab_positions[i].push((Point { x: self.tab_x_offset_pos + self.card_dist_x * i as f32,
-                                                            y: self.tab_y_offset_pos + self.card_dist_y * j as f32},
-                                                    Point { x: self.tab_x_offset_pos + self.card_dist_x * i as f32 + self.card_size_x, 
-                                                            y: self.tab_y_offset_pos + self.card_dist_y + self.card_dist_y * j as f32}));
-                    }
-                    card_index += 1;
-                }  
-    }
-            // add cards left to stock
-            for i in card_index..self.cards.len() {
-                self.cards[card_index].visible = true;
-                self.stock_card_indexes.push(i);
-            }
-            self.stock_area_image_index = Some(100);
+        /// Continues a saved game.
+        pub fn resume(&mut self, game: Game, history: History, clock: Clock) {
+            self.clock = clock;
+            self.cards_to_play = Some(game.cards_to_play.to_string());
+            self.variant = game.variant;
+            self.game = game;
+            self.history = history;
+            self.lay_out();
+            self.hint = None;
+            self.dead = self.game.is_dead();
+            self.focus = None;
+            self.held = None;
             self.start = true;
-   
+            self.recalc_tab_positions();
+            self.redraw_all();
         }
 
-        pub fn move_cards(&mut self, positions: CardPosition) -> Vec<Area> {
-    
-            // Check stock to waste area
-            if point_in_area(positions.from, self.stock_position) 
-                && point_in_area(positions.to, self.waste_position){
-                self.move_stock_to_waste();
-                return vec![Area::Stock, Area::Waste]
+        pub fn undo(&mut self) {
+            if self.history.undo(&mut self.game) {
+                self.hint = None;
+                self.dead = self.game.is_dead();
+                self.held = None;
+                self.recalc_tab_positions();
+                self.redraw_all();
             }
+        }
 
-            // Check waste to stock area
-            if point_in_area(positions.from, self.waste_position) 
-                && point_in_area(positions.to, self.stock_position){
-                self.move_waste_to_stock();
-                return vec![Area::Waste, Area::Stock]
+        pub fn redo(&mut self) {
+            if self.history.redo(&mut self.game) {
+                self.hint = None;
+                self.dead = self.game.is_dead();
+                self.held = None;
+                self.recalc_tab_positions();
+                self.redraw_all();
             }
+        }
 
-            // check if waste to tab
-            let waste_area = point_in_area(positions.from, self.waste_position);
-            let (tab_index_to, _tab_card_index) = self.find_tab_area(positions.to);
-            // waste to tab only uses the tab index not the index of the tab column
-            if tab_index_to.is_some() && waste_area {
-                self.move_waste_to_tab(tab_index_to.unwrap());
-                return vec![Area::Waste, Area::Tableau(tab_index_to.unwrap())]
+        /// Undoes back to the last position that had more than one way to go on.
+        pub fn undo_to_choice(&mut self) {
+            while self.history.undo(&mut self.game) {
+                if self.game.productive_moves().len() > 1 {
+                    break
+                }
             }
+            self.hint = None;
+            self.held = None;
+            self.dead = self.game.is_dead();
+            self.recalc_tab_positions();
+            self.redraw_all();
+        }
 
-            let fd_index_to = self.find_foundation_area(positions.to);
-            // check if waste to fd
-            if fd_index_to.is_some() && waste_area {
-                self.move_waste_to_foundation(fd_index_to.unwrap());
-                return vec![Area::Waste, Area::Foundation(fd_index_to.unwrap())]
+        pub fn redraw_all(&mut self) {
+            for cache in self.tab_cache.iter() {
+                cache.clear();
             }
+            self.waste_cache.clear();
+            self.stock_cache.clear();
+            self.foundation_cache.clear();
+            self.cell_cache.clear();
+        }
 
-            // check if tab
-            let (tab_index_from_opt, tab_card_index_from_opt) = self.find_tab_area(positions.from);
-            
-            // check if tab to tab
-            let (tab_index_to_opt, tab_card_index_to_opt) = self.find_tab_area(positions.to);
-            if tab_index_from_opt.is_some() && tab_index_to_opt.is_some() {
-                self.move_tab_to_tab((tab_index_from_opt.unwrap(), tab_card_index_from_opt.unwrap()), 
-                                    (tab_index_to_opt.unwrap(), tab_card_index_to_opt.unwrap()));
-                return vec![Area::Tableau(tab_index_from_opt.unwrap()), Area::Tableau(tab_index_to.unwrap())]
-            }
-            
-            // check if tab to fd
-            if tab_index_from_opt.is_some() && fd_index_to.is_some()  {
-                self.move_tab_to_foundation(tab_index_from_opt.unwrap(), fd_index_to.unwrap());
-                return vec![Area::Tableau(tab_index_from_opt.unwrap()), Area::Foundation(fd_index_to.unwrap())]
+        /// Highlights the next suggested move, pressing again cycles through
+        /// the alternatives. Returns the place of the hint and how many there are.
+        pub fn next_hint(&mut self) -> Option<(usize, usize)> {
+            let moves = solver::ordered_moves(&self.game);
+            if moves.is_empty() {
+                self.hint = None;
+                return None
             }
 
-            vec![Area::None]
+            let index = match self.hint {
+                Some((index, _)) => (index + 1) % moves.len(),
+                None => 0,
+            };
+            self.hint = Some((index, moves[index]));
+            Some((index, moves.len()))
+        }
+
+        // the source and destination of a move on the canvas
+        fn hint_areas(&self, mv: Move) -> ((Point, Point), (Point, Point)) {
+            let tab_top = |tab: usize| *self.tab_positions[tab].last().unwrap();
+            match mv {
+                Move::StockToWaste => (self.stock_position, self.waste_position),
+                Move::Recycle => (self.waste_position, self.stock_position),
+                Move::WasteToTableau(to) => (self.waste_position, tab_top(to)),
+                Move::WasteToFoundation(to) => (self.waste_position, self.foundation_positions[to]),
+                Move::TableauToTableau { from, depth, to } => {
+                    let tab = &self.tab_positions[from];
+                    ((tab[tab.len() - depth].0, tab_top(from).1), tab_top(to))
+                },
+                Move::TableauToFoundation { from, to } => (tab_top(from), self.foundation_positions[to]),
+                Move::FoundationToTableau { from, to } => (self.foundation_positions[from], tab_top(to)),
+                Move::TableauToCell { from, to } => (tab_top(from), self.cell_positions[to]),
+                Move::CellToTableau { from, to } => (self.cell_positions[from], tab_top(to)),
+                Move::CellToFoundation { from, to } => (self.cell_positions[from], self.foundation_positions[to]),
+                Move::DealRow => (self.stock_position, self.stock_position),
+                Move::TableauPair { from, with } => (tab_top(from), tab_top(with)),
+                Move::WastePair(with) => (self.waste_position, tab_top(with)),
+            }
+        }
 
+        fn card_image(&self, card_index: usize) -> Handle {
+            self.card_images[self.game.cards[card_index].id.index()].clone()
         }
+    }
 
-        pub fn find_tab_area(&mut self, position: Point) -> (Option<usize>, Option<usize>) {
 
-            for i in 0..7 {
-                if self.tab_positions[i].len() == 0 {
-                    continue;
-                }
-                // postion of first card index
-                let first_card = self.tab_positions[i][0].0;
+    impl State {
+        /// Deals the given deal number, or a random one.
+        pub fn start(&mut self, deal: Option<u32>) {
+            let cards_to_play = self.cards_to_play.as_deref().unwrap_or("3").parse().unwrap_or(3);
+            self.game = match deal {
+                Some(deal) => Game::deal(self.variant, deal, cards_to_play),
+                None => Game::shuffled(self.variant, cards_to_play),
+            }.with_scoring(self.scoring).with_passes(self.passes);
+            self.history = History::default();
+            self.lay_out();
+            self.clock = Clock::default();
+            self.hint = None;
+            self.dead = self.game.is_dead();
+            self.focus = None;
+            self.held = None;
+            self.recalc_tab_positions();
+            self.start = true;
+        }
 
-                let last_index = if self.tab_positions[i].len() > 0 {
-                    self.tab_positions[i].len()-1
-                } else {
-                    0
-                };
-                
-                let last_card = self.tab_positions[i][last_index].1;
-                if point_in_area(position, (first_card, last_card)) {
-                    for (index, area) in self.tab_positions[i].iter().enumerate() {
-                        if point_in_area(position, *area) {
-                            return (Some(i), Some(index));
-                        }
-                    } 
-                }
+        pub fn move_cards(&mut self, positions: CardPosition) -> Result<Vec<Area>, MoveError> {
+            match self.move_for(positions) {
+                Some(mv) => self.play(mv),
+                None => Ok(vec![Area::None]),
             }
- 
-            return (None, None)
         }
 
-        pub fn find_foundation_area(&mut self, position: Point) -> Option<usize> {
-
-            for (i, fd_pos) in self.foundation_positions.iter().enumerate() {
-                let found_to = point_in_area(position, fd_pos.clone());
-                if found_to {
-                    return Some(i);
-                }
+        /// Sends the waste card, a free cell card or the tableau top card
+        /// under the point to whichever foundation takes it.
+        pub fn send_to_foundation(&mut self, point: Point) -> Result<Vec<Area>, MoveError> {
+            match self.foundation_move_for(point) {
+                Some(Ok(mv)) => self.play(mv),
+                Some(Err(error)) => Err(error),
+                None => Ok(vec![Area::None]),
             }
-            
-            None
         }
 
-        pub fn move_stock_to_waste(&mut self) {
-            if self.stock_card_indexes.len() == 0 {
-                return
-            }
-            if self.cards_to_play == Some("1".to_string()) {
-                let final_length = self.stock_card_indexes.len().saturating_sub(1);
-                let tail = self.stock_card_indexes.split_off(final_length);
-                self.waste_card_indexes.extend(tail);
+        // None when the point is not on a card that could go up
+        fn foundation_move_for(&self, point: Point) -> Option<Result<Move, MoveError>> {
+            let foundations = 0..self.game.foundation_cards.len();
+            let moves: Vec<Move> = if self.in_waste(point) {
+                if self.game.waste_card_indexes.is_empty() {
+                    return None
+                }
+                foundations.map(Move::WasteToFoundation).collect()
+            } else if let Some(from) = self.find_cell_area(point) {
+                self.game.cell_cards[from]?;
+                foundations.map(|to| Move::CellToFoundation { from, to }).collect()
             } else {
-                if self.stock_card_indexes.len() >= 3 {
-                        let final_length = self.stock_card_indexes.len().saturating_sub(3);
-                        let mut tail = self.stock_card_indexes.split_off(final_length);
-                        tail.reverse();
-                        self.waste_card_indexes.extend(tail);
-                } else if self.stock_card_indexes.len() <= 3 {
-                    self.waste_card_indexes.extend(self.stock_card_indexes.clone());
-                    self.stock_card_indexes = vec![];
-                    self.stock_area_image_index = None;
+                let (Some(from), Some(index)) = self.find_tab_area(point) else {
+                    return None
+                };
+                if index + 1 != self.game.tab_card_indexes[from].len() {
+                    return None
                 }
-            }
+                foundations.map(|to| Move::TableauToFoundation { from, to }).collect()
+            };
 
-            if self.stock_card_indexes.len() == 0 {
-                self.stock_area_image_index = None;
-            } else {
-                self.stock_area_image_index = Some(100);
-            }
+            let found = moves.into_iter().find(|mv| self.game.check(*mv).is_ok());
+            Some(found.ok_or(MoveError::NoFoundation))
+        }
 
-            // show top card
-            self.waste_area_image_index = if self.waste_card_indexes.len() > 0 {
-                Some(*self.waste_card_indexes.last().unwrap())
-            } else {
-                None
-            };
+        /// Plays a move so it can be undone, returns the piles it changed.
+        pub fn play(&mut self, mv: Move) -> Result<Vec<Area>, MoveError> {
+            let before = self.game.clone();
+            self.game.apply(mv)?;
+            let foundations_changed = before.foundation_cards != self.game.foundation_cards;
+            self.history.record(before);
+            self.hint = None;
+            self.dead = self.game.is_dead();
+            let mut areas = areas_for(mv, self.game.tab_card_indexes.len());
+            // finished runs go up on their own in Spider
+            if foundations_changed && !areas.iter().any(|area| matches!(area, Area::Foundation(_))) {
+                areas.push(Area::Foundation(0));
+            }
+            Ok(areas)
         }
 
-        // move cards back only if stock is empty
-        pub fn move_waste_to_stock(&mut self) {
-            if self.stock_card_indexes.is_empty() {
-                if self.waste_card_indexes.is_empty() {
-                    return
+        /// Plays every remaining card to the foundations, each one as its own move.
+        pub fn auto_finish(&mut self) -> Vec<Area> {
+            let mut areas = vec![];
+            if !self.game.can_auto_finish() {
+                return areas
+            }
+            while let Some(mv) = self.game.finish_move() {
+                match self.play(mv) {
+                    Ok(changed) => areas.extend(changed),
+                    Err(_) => break,
                 }
-                self.waste_card_indexes.reverse();
-                self.stock_card_indexes = self.waste_card_indexes.clone();
-                self.waste_card_indexes = vec![];
-                self.stock_area_image_index = Some(100);
-                self.waste_area_image_index = None;
-                self.card_rounds += 1;
-                self.cards_moved_from_waste = 0;
             }
+            areas
         }
 
-        pub fn move_waste_to_tab(&mut self, tab_index: usize) {
-            let waste_card_index_opt = self.waste_card_indexes.last();
-            let last_tab_card_index_opt = self.tab_card_indexes[tab_index].last();
+        /// Translates a source and destination click into the move the player meant.
+        pub fn move_for(&self, positions: CardPosition) -> Option<Move> {
 
-            let last_tab_card_index_opt = match last_tab_card_index_opt {
-                Some(index) => Some(*index),
-                None => None,
-            };
+            if self.deals_from(positions.from) {
+                return Some(Move::DealRow)
+            }
+
+            // Check stock to waste area
+            if self.in_stock(positions.from) && self.in_waste(positions.to) {
+                return Some(Move::StockToWaste)
+            }
+
+            // Check waste to stock area
+            if self.in_waste(positions.from) && self.in_stock(positions.to) {
+                return Some(Move::Recycle)
+            }
 
-            let king_card: Option<usize> = if waste_card_index_opt.is_some() {
-                if self.cards[*waste_card_index_opt.unwrap()].value == 13 {
-                    Some(13)
+            let waste_area = self.in_waste(positions.from);
+            // waste to tab only uses the tab index not the index of the tab column
+            let (tab_index_to, _tab_card_index) = self.find_tab_area(positions.to);
+            let fd_index_to = self.find_foundation_area(positions.to);
+            // cards are paired up instead of built on each other
+            let pairs = matches!(self.layout.shape, Shape::Pyramid { .. });
+
+            if waste_area {
+                if let Some(to) = tab_index_to {
+                    return Some(if pairs { Move::WastePair(to) } else { Move::WasteToTableau(to) })
                 }
-                else {
-                    None
+                if let Some(to) = fd_index_to {
+                    return Some(Move::WasteToFoundation(to))
                 }
-            } else {
-                None
-            };
+                return None
+            }
 
-            if waste_card_index_opt.is_some() && (last_tab_card_index_opt.is_some() || king_card.is_some()) {
-                // if tab is empty then a king can be put in
-                if waste_card_index_opt.is_some() && last_tab_card_index_opt.is_none() {
-                    let waste_card_index = waste_card_index_opt.unwrap();
-                    if king_card.is_some() {
-                        self.cards[*waste_card_index].visible = true;
-                        self.tab_card_indexes[tab_index].push(*waste_card_index);
-                        let final_length = self.waste_card_indexes.len().saturating_sub(1);
-                        self.waste_card_indexes.truncate(final_length);
-                        let last_waste_card_opt = self.waste_card_indexes.last();
-                        if last_waste_card_opt.is_some() {
-                            let last_card = *last_waste_card_opt.unwrap();
-                            self.waste_area_image_index = Some(last_card);
-                            self.cards_moved_from_waste += 1;
-                        }
-                    }
-                    return
-                }
+            if let Some(from) = self.find_foundation_area(positions.from) {
+                return tab_index_to.map(|to| Move::FoundationToTableau { from, to })
+            }
 
-                let last_card_index = last_tab_card_index_opt.unwrap();
-                let waste_card_index = waste_card_index_opt.unwrap();
-                let last_card = &self.cards[last_card_index];
-                let waste_card = &self.cards[*waste_card_index];
-
-                // check the value and color
-                if last_card.value == waste_card.value + 1 && last_card.color != waste_card.color {
-                    self.cards[*waste_card_index].visible = true;
-                    self.tab_card_indexes[tab_index].push(*waste_card_index);
-                    let final_length = self.waste_card_indexes.len().saturating_sub(1);
-                    self.waste_card_indexes.truncate(final_length);
-                    let last_waste_card_opt = self.waste_card_indexes.last();
-                    if last_waste_card_opt.is_some() {
-                        let last_card = *last_waste_card_opt.unwrap();
-                        self.waste_area_image_index = Some(last_card);
-                    }
-                    self.cards_moved_from_waste += 1;
-                    return
-                } else {
-                    return
+            if let Some(from) = self.find_cell_area(positions.from) {
+                if let Some(to) = tab_index_to {
+                    return Some(Move::CellToTableau { from, to })
                 }
+                return fd_index_to.map(|to| Move::CellToFoundation { from, to })
             }
-            
-        }
 
-        pub fn move_waste_to_foundation(&mut self, fd_index: usize) {
-            let waste_card_index_opt = self.waste_card_indexes.last();
-            let waste_card_index = match waste_card_index_opt {
-                Some(index) => *index,
-                None => return,
+            // check if tab
+            let (Some(from), Some(tab_card_index_from)) = self.find_tab_area(positions.from) else {
+                return None
             };
-            
-            // validate move ace to empty fd
-            // fd index of 100 indicates empty 
-            if self.foundation_cards[fd_index] == 100 && self.cards[waste_card_index].value != 1 {
-                return
-            }
-            
-            if self.foundation_cards[fd_index] != 100 {
-                let fd_card_index = self.foundation_cards[fd_index];
-                // value waste must be 1 more than fd card
-                if self.cards[fd_card_index].value != self.cards[waste_card_index].value-1 {
-                    return
-                }
-                // colors must equal
-                if self.cards[fd_card_index].color != self.cards[waste_card_index].color {
-                    return
+            let depth = self.game.tab_card_indexes[from].len().saturating_sub(tab_card_index_from);
+
+            if let Some(to) = tab_index_to {
+                if pairs {
+                    return Some(Move::TableauPair { from, with: to })
                 }
+                return Some(Move::TableauToTableau { from, depth, to })
+            }
 
-                if self.cards[fd_card_index].suite != self.cards[waste_card_index].suite {
-                    return
-                } 
+            if pairs && self.in_waste(positions.to) {
+                return Some(Move::WastePair(from))
             }
-            
-            // move card   
-            self.foundation_cards[fd_index] = waste_card_index;
-            let final_length = self.waste_card_indexes.len().saturating_sub(1);
-            self.waste_card_indexes.truncate(final_length);
-            let last_waste_card_opt = self.waste_card_indexes.last();
-            if last_waste_card_opt.is_some() {
-                self.waste_area_image_index = Some(*last_waste_card_opt.unwrap());
-            } else {
-                self.waste_area_image_index = None;
+
+            // a free cell takes the top card only
+            if let Some(to) = self.find_cell_area(positions.to) {
+                return (depth == 1).then_some(Move::TableauToCell { from, to })
             }
-            self.cards_moved_from_waste += 1;
-            
+
+            fd_index_to.map(|to| Move::TableauToFoundation { from, to })
         }
 
-        fn move_tab_to_tab(&mut self, (tab_index_from, from_index): (usize, usize), 
-                                        (tab_index_to, _to_index): (usize, usize)) {
-            // if the selected card is last, just move it
-            // if the selected card is not last it means we are moving many cards
-            // The move to will always be appending to the tab
-            let from_len = self.tab_card_indexes[tab_index_from].len();
-            let moving_last = if from_len-1 == from_index {
-                true
-            } else {
-                false
-            };
+        /// Cards picked up by pressing on the point with where each one is
+        /// drawn, empty when there is nothing there that can move.
+        pub fn dragged_cards(&self, point: Point) -> Vec<(usize, Point)> {
+            if self.in_waste(point) {
+                return self.game.waste_card_indexes.last()
+                    .map(|index| vec![(*index, self.waste_position.0)])
+                    .unwrap_or_default()
+            }
+            if let Some(fd_index) = self.find_foundation_area(point) {
+                return self.game.foundation_cards[fd_index]
+                    .map(|index| vec![(index, self.foundation_positions[fd_index].0)])
+                    .unwrap_or_default()
+            }
+            if let Some(cell) = self.find_cell_area(point) {
+                return self.game.cell_cards[cell]
+                    .map(|index| vec![(index, self.cell_positions[cell].0)])
+                    .unwrap_or_default()
+            }
 
-            let card_from_index_opt: Option<&usize> = if moving_last {
-                self.tab_card_indexes[tab_index_from].last()
-            } else {
-                Some(&self.tab_card_indexes[tab_index_from][from_index])
+            let (Some(from), Some(index)) = self.find_tab_area(point) else {
+                return vec![]
             };
-            // can unwrap due to the check in the calling method
-            let card_from_index = *card_from_index_opt.unwrap();
-        
-            let card_to_index_opt = self.tab_card_indexes[tab_index_to].last();
-
-            // move if king
-            if card_to_index_opt.is_none() && self.cards[card_from_index].value == 13 {
-                //continue
-            } else {
-                let card_to_index = *card_to_index_opt.unwrap();
-                if self.cards[card_from_index].color == self.cards[card_to_index].color {
-                    return
-                } else {
-                    if self.cards[card_from_index].value+1 != self.cards[card_to_index].value {
-                        return
+            let tab = &self.game.tab_card_indexes[from];
+            if index >= tab.len() || !self.game.cards[tab[index]].visible {
+                return vec![]
+            }
+            tab[index..].iter()
+                .zip(self.tab_positions[from][index..].iter())
+                .map(|(card_index, area)| (*card_index, area.0))
+                .collect()
+        }
+
+        /// Moves the keyboard focus or picks up and drops cards, a drop comes
+        /// back as the move to play.
+        pub fn key(&mut self, key: Key) -> Option<CardPosition> {
+            let focus = self.focused();
+            let top_piles = self.top_piles().len();
+            let columns = self.layout.columns;
+
+            self.focus = Some(match (key, focus) {
+                (Key::Left, Focus::Top(pile)) => Focus::Top((pile + top_piles - 1) % top_piles),
+                (Key::Right, Focus::Top(pile)) => Focus::Top((pile + 1) % top_piles),
+                (Key::Left, Focus::Tableau(column, _)) => self.column_top((column + columns - 1) % columns),
+                (Key::Right, Focus::Tableau(column, _)) => self.column_top((column + 1) % columns),
+                (Key::Up, Focus::Tableau(column, card)) => {
+                    if card > self.first_face_up(column) {
+                        Focus::Tableau(column, card - 1)
+                    } else {
+                        Focus::Top(self.pile_above(column))
                     }
-                }
+                },
+                (Key::Down, Focus::Top(pile)) => self.column_top(self.column_below(pile)),
+                (Key::Down, Focus::Tableau(column, card)) => {
+                    Focus::Tableau(column, (card + 1).min(self.column_top_index(column)))
+                },
+                (Key::Column(column), _) if column < columns => self.column_top(column),
+                (Key::Column(_), focus) => focus,
+                (Key::Up, focus) | (Key::Select, focus) | (Key::Cancel, focus) => focus,
+            });
+
+            match key {
+                Key::Select => {
+                    let point = self.focus_point(focus);
+                    match self.held.take() {
+                        // dropping where it was picked up puts it back
+                        Some(from) if from == point => None,
+                        Some(from) => Some(CardPosition { from, to: point }),
+                        None if focus == Focus::Top(0) && self.layout.stock => {
+                            if !self.layout.waste {
+                                Some(CardPosition { from: point, to: point })
+                            } else if self.game.stock_card_indexes.is_empty() {
+                                Some(CardPosition { from: self.waste_position.0 + FOCUS_OFFSET, to: point })
+                            } else {
+                                Some(CardPosition { from: point, to: self.waste_position.0 + FOCUS_OFFSET })
+                            }
+                        },
+                        None => {
+                            self.held = self.selection(point).map(|_| point);
+                            None
+                        },
+                    }
+                },
+                Key::Cancel => {
+                    self.held = None;
+                    None
+                },
+                _ => None,
             }
+        }
 
-            // remove last card from
-            if moving_last {
-                let final_length = self.tab_card_indexes[tab_index_from].len().saturating_sub(1);
-                self.tab_card_indexes[tab_index_from].truncate(final_length);
-                self.tab_card_indexes[tab_index_to].push(card_from_index);
-                
+        /// Draws from the stock, or turns the waste over once the stock is empty.
+        /// Without a waste the stock deals a row instead.
+        pub fn stock_move(&self) -> Move {
+            if !self.layout.waste {
+                Move::DealRow
+            } else if self.game.stock_card_indexes.is_empty() {
+                Move::Recycle
             } else {
-                // move many
-                let moving_indexes = self.tab_card_indexes[tab_index_from].split_off(from_index);
-                self.tab_card_indexes[tab_index_to].extend(moving_indexes);
+                Move::StockToWaste
+            }
+        }
+
+        // the focus kept inside the cards that are there now
+        fn focused(&self) -> Focus {
+            match self.focus {
+                Some(Focus::Tableau(column, card)) => {
+                    let card = card.min(self.column_top_index(column)).max(self.first_face_up(column));
+                    Focus::Tableau(column, card)
+                },
+                Some(focus) => focus,
+                None => Focus::Top(0),
             }
-            
+        }
 
-            // turn over the last card if not empty
-            let card_opt = self.tab_card_indexes[tab_index_from].last();
-            match card_opt {
-                Some(index) => self.cards[*index].visible = true,
-                None => (),
+        // the stock, waste, free cells and foundations the keyboard steps through
+        fn top_piles(&self) -> Vec<(Point, Point)> {
+            let mut piles = vec![];
+            if self.layout.stock {
+                piles.push(self.stock_position);
+            }
+            if self.layout.waste {
+                piles.push(self.waste_position);
             }
+            piles.extend(self.cell_positions.iter().copied());
+            piles.extend(self.foundation_positions.iter().copied());
+            piles
+        }
+
+        // the top row pile nearest above a column
+        fn pile_above(&self, column: usize) -> usize {
+            let x = self.column_origin(column).x;
+            self.top_piles().iter().enumerate()
+                .min_by(|(_, a), (_, b)| (a.0.x - x).abs().total_cmp(&(b.0.x - x).abs()))
+                .map_or(0, |(pile, _)| pile)
+        }
 
+        // the column nearest below a top row pile
+        fn column_below(&self, pile: usize) -> usize {
+            (0..self.layout.columns)
+                .find(|column| self.pile_above(*column) == pile)
+                .unwrap_or(0)
         }
 
-        pub fn move_tab_to_foundation(&mut self, tab_index: usize, fd_index: usize) {
-            let tab_card_index_opt = self.tab_card_indexes[tab_index].last();
-            let tab_card_index = match tab_card_index_opt {
-                Some(index) => *index,
-                None => return,
+        fn column_top(&self, column: usize) -> Focus {
+            Focus::Tableau(column, self.column_top_index(column))
+        }
+
+        fn column_top_index(&self, column: usize) -> usize {
+            self.game.tab_card_indexes[column].len().saturating_sub(1)
+        }
+
+        fn first_face_up(&self, column: usize) -> usize {
+            let tab = &self.game.tab_card_indexes[column];
+            tab.iter().position(|index| self.game.cards[*index].visible)
+                .unwrap_or(tab.len())
+                .min(self.column_top_index(column))
+        }
+
+        fn focus_point(&self, focus: Focus) -> Point {
+            let top_left = match focus {
+                Focus::Top(pile) => self.top_piles()[pile].0,
+                Focus::Tableau(column, card) => self.tab_positions[column][card].0,
             };
- 
-            // validate move ace to empty fd
-            // fd index of 100 indicates empty 
-            if fd_index == 100 && self.cards[tab_card_index].value != 1 {
-                return
+            top_left + FOCUS_OFFSET
+        }
+
+        // the outline of the focused pile or cards
+        fn focus_area(&self) -> Option<(Point, Point)> {
+            // nothing is outlined until a key is pressed
+            self.focus?;
+            match self.focused() {
+                Focus::Tableau(column, card) => {
+                    let tab = &self.tab_positions[column];
+                    Some((tab[card].0, Point { x: tab[card].1.x, y: tab[tab.len() - 1].0.y + self.card_size_y }))
+                },
+                Focus::Top(pile) => self.top_piles().get(pile).copied(),
             }
-            
-            if self.foundation_cards[fd_index] != 100 {
-                let fd_card_index = self.foundation_cards[fd_index];
-                // value fd must be 1 less than waste card
-                if self.cards[fd_card_index].value != self.cards[tab_card_index].value-1 {
-                    return
-                }
-                // colors must equal
-                if self.cards[fd_card_index].color != self.cards[tab_card_index].color {
-                    return
-                }
+        }
 
-                if self.cards[fd_card_index].suite != self.cards[tab_card_index].suite {
-                    return
-                } 
+        /// Outline of the cards selected by a click on the point.
+        pub fn selection(&self, point: Point) -> Option<(Point, Point)> {
+            if self.in_stock(point) {
+                return (!self.game.stock_card_indexes.is_empty()).then_some(self.stock_position)
+            }
+
+            let cards = self.dragged_cards(point);
+            let (Some((_, first)), Some((_, last))) = (cards.first(), cards.last()) else {
+                return None
+            };
+            Some((*first, Point { x: last.x + self.card_size_x, y: last.y + self.card_size_y }))
+        }
+
+        /// Piles the selection made at the point can legally be moved to.
+        pub fn drop_targets(&self, from: Point) -> Vec<(Point, Point)> {
+            let mut areas = self.top_piles();
+            areas.extend(self.tab_positions.iter().filter_map(|tab| tab.last().copied()));
+
+            areas.into_iter()
+                .filter(|area| {
+                    let to = Point { x: (area.0.x + area.1.x) / 2.0, y: (area.0.y + area.1.y) / 2.0 };
+                    self.move_for(CardPosition { from, to })
+                        .is_some_and(|mv| self.game.check(mv).is_ok())
+                })
+                .collect()
+        }
+
+        pub fn find_tab_area(&self, position: Point) -> (Option<usize>, Option<usize>) {
+
+            // each card of a pyramid lies on the two above it, so the lowest
+            // card under the point is the one clicked
+            if let Shape::Pyramid { .. } = self.layout.shape {
+                return (0..self.tab_positions.len()).rev()
+                    .find(|slot| {
+                        !self.game.tab_card_indexes[*slot].is_empty()
+                            && point_in_area(position, self.tab_positions[*slot][0])
+                    })
+                    .map_or((None, None), |slot| (Some(slot), Some(0)))
+            }
+
+            for (i, tab) in self.tab_positions.iter().enumerate() {
+                let (Some(first), Some(last)) = (tab.first(), tab.last()) else {
+                    continue;
+                };
+                // postion of first card index and the bottom of the last
+                if point_in_area(position, (first.0, last.1)) {
+                    for (index, area) in tab.iter().enumerate() {
+                        if point_in_area(position, *area) {
+                            return (Some(i), Some(index));
+                        }
+                    }
+                }
             }
-            // move card
-            self.foundation_cards[fd_index] = tab_card_index;
-            let final_length = self.tab_card_indexes[tab_index].len().saturating_sub(1);
-            self.tab_card_indexes[tab_index].truncate(final_length);
-            // show last card
-            let last_card_index_opt = self.tab_card_indexes[tab_index].last();
-            match last_card_index_opt {
-                Some(index) => self.cards[*index].visible = true,
-                None => ()
+
+            (None, None)
+        }
+
+        pub fn find_foundation_area(&self, position: Point) -> Option<usize> {
+            self.foundation_positions.iter()
+                .position(|fd_pos| point_in_area(position, *fd_pos))
+        }
+
+        pub fn find_cell_area(&self, position: Point) -> Option<usize> {
+            self.cell_positions.iter()
+                .position(|cell_pos| point_in_area(position, *cell_pos))
+        }
+
+        // where the first card of a column goes, the slots of a pyramid move
+        // in by half a card and down by a third of one on every row
+        fn column_origin(&self, column: usize) -> Point {
+            match self.layout.shape {
+                Shape::Columns => Point {
+                    x: self.tab_x_offset_pos + self.card_dist_x * column as f32,
+                    y: self.tab_y_offset_pos,
+                },
+                Shape::Pyramid { rows } => {
+                    let (row, place) = row_and_place(column);
+                    let steps = (rows - 1 - row) as f32 / 2.0 + place as f32;
+                    Point {
+                        x: self.tab_x_offset_pos + self.card_dist_x * steps,
+                        y: self.tab_y_offset_pos + self.card_size_y / 3.0 * row as f32,
+                    }
+                },
             }
-            
         }
 
         pub fn recalc_tab_positions(&mut self) {
-            self.tab_positions = vec![vec![]; 7];
-            for i in 0..7 {
-                if self.tab_card_indexes[i].is_empty() {
-                    self.tab_positions[i] = vec![(Point { x: self.tab_x_offset_pos + self.card_dist_x * i as f32, 
-                                                            y: self.tab_y_offset_pos},
-                                                    Point { x: self.tab_x_offset_pos + self.card_dist_x * i as f32 + self.card_size_x, 
-                                                            y: self.tab_y_offset_pos + self.card_dist_y + self.card_size_y})];
-            
+            self.tab_positions = vec![vec![]; self.game.tab_card_indexes.len()];
+            for i in 0..self.tab_positions.len() {
+                let Point { x, y: top } = self.column_origin(i);
+                if let Shape::Pyramid { .. } = self.layout.shape {
+                    // a slot holds one card, the whole of it even where the
+                    // row below covers it
+                    self.tab_positions[i] = vec![(Point { x, y: top },
+                                                    Point { x: x + self.card_size_x, y: top + self.card_size_y })];
+                } else if self.game.tab_card_indexes[i].is_empty() {
+                    self.tab_positions[i] = vec![(Point { x, y: top },
+                                                    Point { x: x + self.card_size_x,
+                                                            y: top + self.card_dist_y + self.card_size_y})];
+
                 } else {
-                 
-                    for (j, card_index) in self.tab_card_indexes[i].iter().enumerate() {
-                        if self.cards[*card_index].visible {
-                            self.tab_positions[i].push((Point { x: self.tab_x_offset_pos + self.card_dist_x * i as f32, 
-                                                                y: self.tab_y_offset_pos + self.card_dist_y * j as f32},
-                                                        Point { x: self.tab_x_offset_pos + self.card_dist_x * i as f32 + self.card_size_x, 
-                                                                y: self.tab_y_offset_pos + self.card_dist_y + self.card_dist_y * j as f32 + self.card_size_y}));
+
+                    let last = self.game.tab_card_indexes[i].len() - 1;
+                    for j in 0..=last {
+                        // only the last card shows its full height, the others
+                        // are covered by the card on top of them
+                        let height = if j == last {
+                            self.card_dist_y + self.card_size_y
                         } else {
-                            self.tab_positions[i].push((Point { x: self.tab_x_offset_pos + self.card_dist_x * i as f32,
-                                                                y: self.tab_y_offset_pos + self.card_dist_y * j as f32},
-                                                        Point { x: self.tab_x_offset_pos + self.card_dist_x * i as f32 + self.card_size_x, 
-                                                                y: self.tab_y_offset_pos + self.card_dist_y + self.card_dist_y * j as f32}));
-                        }
-                        
+                            self.card_dist_y
+                        };
+                        let y = top + self.card_dist_y * j as f32;
+                        self.tab_positions[i].push((Point { x, y },
+                                                    Point { x: x + self.card_size_x, y: y + height }));
                     }
                 }
-            }  
+            }
+        }
+
+        fn in_stock(&self, point: Point) -> bool {
+            self.layout.stock && point_in_area(point, self.stock_position)
+        }
+
+        fn in_waste(&self, point: Point) -> bool {
+            self.layout.waste && point_in_area(point, self.waste_position)
+        }
+
+        // a stock without a waste deals onto the columns
+        fn deals_from(&self, point: Point) -> bool {
+            !self.layout.waste && self.in_stock(point)
         }
 
         fn is_point_in_any_area(&self, point: Point) -> bool {
-  
-            if point_in_area(point, self.stock_position) {return true}
-            if point_in_area(point, self.waste_position) {return true}
-            
-            for area in self.foundation_positions.iter() {
-                if point_in_area(point, *area) {return true}
-            }
-           
+
+            if self.top_piles().iter().any(|area| point_in_area(point, *area)) {return true}
+
             for area in self.tab_positions.iter() {
                 let top_left = area[0].0;
-                let mut bottom_right = Point{ x: top_left.x+100.0, y: top_left.y+175.0 };
-                if area.len() > 1 {
-                    let last = area[area.len()-1];
-                    bottom_right = last.1
-                }
-                
+                let bottom_right = area[area.len()-1].1;
+
                 if point_in_area(point, (top_left, bottom_right)) {return true}
             }
 
@@ -692,18 +1422,31 @@ mod board {
         }
     }
 
-    fn point_in_area(position: Point, area_to: (Point, Point)) -> bool {
-        let to = if position.x >= area_to.0.x && position.x <= area_to.1.x {
-            if position.y >= area_to.0.y && position.y <= area_to.1.y {
-            true
-            } else {
-                false
-            }
-        } else {
-            false
-        };
+    // the piles that need redrawing after a move
+    fn areas_for(mv: Move, columns: usize) -> Vec<Area> {
+        match mv {
+            Move::StockToWaste | Move::Recycle => vec![Area::Stock, Area::Waste],
+            Move::WasteToTableau(to) => vec![Area::Waste, Area::Tableau(to)],
+            Move::WasteToFoundation(to) => vec![Area::Waste, Area::Foundation(to)],
+            Move::TableauToTableau { from, to, .. } => vec![Area::Tableau(from), Area::Tableau(to)],
+            Move::TableauToFoundation { from, to } => vec![Area::Tableau(from), Area::Foundation(to)],
+            Move::FoundationToTableau { from, to } => vec![Area::Foundation(from), Area::Tableau(to)],
+            Move::TableauToCell { from, to } => vec![Area::Tableau(from), Area::Cell(to)],
+            Move::CellToTableau { from, to } => vec![Area::Cell(from), Area::Tableau(to)],
+            Move::CellToFoundation { from, to } => vec![Area::Cell(from), Area::Foundation(to)],
+            Move::DealRow => std::iter::once(Area::Stock).chain((0..columns).map(Area::Tableau)).collect(),
+            Move::TableauPair { from, with } => vec![Area::Tableau(from), Area::Tableau(with), Area::Foundation(0)],
+            Move::WastePair(with) => vec![Area::Waste, Area::Tableau(with), Area::Foundation(0)],
+        }
+    }
 
-        to
+    fn area_rectangle(area: (Point, Point)) -> Path {
+        Path::rectangle(area.0, iced::Size::new(area.1.x - area.0.x, area.1.y - area.0.y))
+    }
+
+    fn point_in_area(position: Point, area_to: (Point, Point)) -> bool {
+        position.x >= area_to.0.x && position.x <= area_to.1.x
+            && position.y >= area_to.0.y && position.y <= area_to.1.y
     }
 
     #[derive(Debug, Clone, Copy, PartialEq)]
@@ -713,6 +1456,7 @@ mod board {
         Stock,
         Waste,
         Tableau(usize), // tab index
+        Cell(usize), // free cell index
     }
 
     #[derive(Debug, Clone, Copy)]
@@ -721,75 +1465,133 @@ mod board {
         to: Point,
     }
 
+    /// Keys that drive the board, see `key_message` for the bindings.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Key {
+        Left,
+        Right,
+        Up,
+        Down,
+        Column(usize),
+        Select,
+        Cancel,
+    }
+
+    // room for the columns, the cards get smaller when more are dealt
+    const BOARD_WIDTH: f32 = 870.0;
+    // keyboard picks land this far into the card, clear of its edges
+    const FOCUS_OFFSET: iced::Vector = iced::Vector::new(10.0, 10.0);
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Focus {
+        // index into the stock, waste, free cells and foundations the variant has
+        Top(usize),
+        // column and card within it
+        Tableau(usize, usize),
+    }
+
+    /// What the player did on the canvas.
+    #[derive(Debug, Clone, Copy)]
+    pub enum Action {
+        Move(CardPosition),
+        ToFoundation(Point),
+    }
+
+    // a second click this soon on the same spot is a double click
+    const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+    const DOUBLE_CLICK_DISTANCE: f32 = 5.0;
+
     #[derive(Debug, Clone, Copy)]
     enum Pending {
-        One { from: Point },
-        // Two { from: Point, to: Point },
+        One { from: Point, at: Instant },
+        // the button is still down on the cards picked up at `from`
+        Drag { from: Point, at: Instant },
     }
 
     impl Pending {
         fn draw(
             &self,
+            board: &State,
             renderer: &Renderer,
-            _theme: &Theme,
             bounds: Rectangle,
-            _cursor: mouse::Cursor,
+            cursor: mouse::Cursor,
         ) -> Geometry {
-            let frame = Frame::new(renderer, bounds.size());
+            let mut frame = Frame::new(renderer, bounds.size());
+
+            let (Pending::One { from, .. } | Pending::Drag { from, .. }) = self;
+            for target in board.drop_targets(*from) {
+                frame.fill(&area_rectangle(target), Color { a: 0.25, ..Color::from_rgb(0.2, 0.9, 0.3) });
+            }
+            if let (Pending::One { .. }, Some(selection)) = (self, board.selection(*from)) {
+                frame.stroke(&area_rectangle(selection),
+                             Stroke{ style: Style::Solid(Color::from_rgb(0.3, 0.6, 1.0)), width: 4.0, ..Default::default()});
+            }
+
+            if let (Pending::Drag { from, .. }, Some(cursor_position)) = (self, cursor.position_in(bounds)) {
+                let size = iced::Size { width: board.card_size_x, height: board.card_size_y };
+                let offset = cursor_position - *from;
+                let cards = board.dragged_cards(*from);
+
+                // shade the cards left behind so the ones under the cursor stand out
+                if let (Some((_, first)), Some((_, last))) = (cards.first(), cards.last()) {
+                    let left = Path::rectangle(*first, iced::Size { height: last.y - first.y + size.height, ..size });
+                    frame.fill(&left, Color { a: 0.5, ..Color::BLACK });
+                }
+                for (index, position) in cards {
+                    frame.draw_image(
+                        Rectangle::new(position + offset, size),
+                        canvas::Image::new(board.card_image(index))
+                    );
+                }
+            }
+
             frame.into_geometry()
         }
     }
 
-    #[derive(Clone, Debug)]
-    pub struct Card {
-        suite: String,
-        color: String,
-        value: u32,
-        visible: bool,
-        image: Handle,
-    }
-
+    // images are ordered by CardId
+    fn load_card_images() -> Vec<Handle> {
+        let mut images = Vec::with_capacity(52);
 
-    fn load_cards() -> Vec<Card> {
-        let mut cards_ordered: Vec<Card> = Vec::with_capacity(52);
-
-        for suite in ["clubs", "spades", "hearts", "diamonds"] {
-            for i in 1..=13 {
+        for suit in Suit::ALL {
+            for rank in Rank::ALL {
                 let path = format!("{}/assets/cards/{}/{}.png",
-                env!("CARGO_MANIFEST_DIR"), suite, i);
-                let color = if suite == "clubs" || suite == "spades" {
-                    "black".to_string()
-                } else {
-                    "red".to_string()
-                };
-                
-                cards_ordered.push(Card { 
-                                    suite: suite.to_string(), 
-                                    color,  
-                                    value: i,
-                                    visible: false,
-                                    image: Handle::from_path(path),
-                });
+                env!("CARGO_MANIFEST_DIR"), suit.name(), rank.value());
+                images.push(Handle::from_path(path));
             }
         }
-        // make a random vec
-        let mut rand_vec: Vec<u32> = (0..52).collect();
-        rand_vec.shuffle(&mut thread_rng());
-
-        let mut cards: Vec<Card> = vec![];
-        for n in rand_vec {
-            cards.push(cards_ordered[n as usize].clone())
-        }
-
-        cards
 
+        images
     }
 
     struct CardsDraw<'a> {
         state: &'a State,
     }
 
-    impl<'a> canvas::Program<CardPosition> for CardsDraw<'a> {
+    impl CardsDraw<'_> {
+        // piles showing only their top card, an outline when empty
+        fn draw_piles(&self, frame: &mut Frame, piles: &[Option<usize>], positions: &[(Point, Point)], size: iced::Size) {
+            for (i, card_index) in piles.iter().enumerate() {
+                if let Some(index) = card_index {
+                    frame.draw_image(
+                    Rectangle::new(positions[i].0, size),
+                    canvas::Image::new(self.state.card_image(*index))
+                    );
+                } else {
+                    let rectangle = Path::rectangle(positions[i].0, size);
+
+                    let style = Style::Solid(Color::WHITE);
+
+                    let stroke = Stroke{ style, width: 2.0, ..Default::default()};
+
+                    frame.stroke(&rectangle, stroke);
+
+                }
+            }
+        }
+    }
+
+    impl canvas::Program<Action> for CardsDraw<'_> {
         type State = Option<Pending>;
 
         fn update(
@@ -798,7 +1600,7 @@ mod board {
             event: Event,
             bounds: Rectangle,
             cursor: mouse::Cursor,
-        ) -> (event::Status, Option<CardPosition>) {
+        ) -> (event::Status, Option<Action>) {
             let Some(cursor_position) = cursor.position_in(bounds) else {
                 return (event::Status::Ignored, None);
             };
@@ -808,30 +1610,71 @@ mod board {
                     let message = match mouse_event {
                         mouse::Event::ButtonPressed(mouse::Button::Left) => {
                             match *state {
-                                None => {
-                                    if self.state.is_point_in_any_area(cursor_position) {
+                                // a drag released outside the canvas starts over
+                                None | Some(Pending::Drag { .. }) => {
+                                    // a row is dealt without picking a destination
+                                    if self.state.deals_from(cursor_position) {
+                                        *state = None;
+                                        return (event::Status::Captured, Some(Action::Move(CardPosition {
+                                            from: cursor_position,
+                                            to: cursor_position,
+                                        })))
+                                    }
+
+                                    if self.state.drag && !self.state.dragged_cards(cursor_position).is_empty() {
+                                        *state = Some(Pending::Drag {
+                                            from: cursor_position,
+                                            at: Instant::now(),
+                                        });
+                                    } else if self.state.is_point_in_any_area(cursor_position) {
                                         *state = Some(Pending::One {
                                             from: cursor_position,
+                                            at: Instant::now(),
                                         });
                                     } else {
                                         *state = None;
                                     }
-                                    
+
                                     None
                                 }
-                                Some(Pending::One { from }) => {
+                                Some(Pending::One { from, at }) => {
                                     *state = None;
-                                    if self.state.is_point_in_any_area(cursor_position) {
-                                        Some(CardPosition {
+                                    if at.elapsed() < DOUBLE_CLICK
+                                        && from.distance(cursor_position) < DOUBLE_CLICK_DISTANCE {
+                                        Some(Action::ToFoundation(from))
+                                    } else if self.state.is_point_in_any_area(cursor_position) {
+                                        Some(Action::Move(CardPosition {
                                             from,
                                             to: cursor_position,
-                                        })
+                                        }))
                                     } else {
                                         None
                                     }
                                 }
                             }
                         }
+                        mouse::Event::ButtonReleased(mouse::Button::Left) => {
+                            match *state {
+                                // released where it was picked up, carry on as a click
+                                Some(Pending::Drag { from, at }) if from.distance(cursor_position) < DOUBLE_CLICK_DISTANCE => {
+                                    *state = Some(Pending::One { from, at });
+                                    None
+                                }
+                                // an illegal drop leaves the cards where they were
+                                Some(Pending::Drag { from, .. }) => {
+                                    *state = None;
+                                    Some(Action::Move(CardPosition {
+                                        from,
+                                        to: cursor_position,
+                                    }))
+                                }
+                                _ => None,
+                            }
+                        }
+                        mouse::Event::ButtonPressed(mouse::Button::Right) => {
+                            *state = None;
+                            Some(Action::ToFoundation(cursor_position))
+                        }
                         _ => None,
                     };
 
@@ -845,7 +1688,7 @@ mod board {
             &self,
             state: &Self::State,
             renderer: &Renderer,
-            theme: &Theme,
+            _theme: &Theme,
             bounds: Rectangle,
             cursor: mouse::Cursor,
         ) -> Vec<Geometry> {
@@ -853,20 +1696,20 @@ mod board {
                 return vec![]
             }
             let mut geometries = vec![];
-            
+
             geometries.push(self.state.table_cache.draw(renderer, bounds.size(), |frame| {
                     frame.fill_rectangle(iced::Point::ORIGIN, frame.size(), Color::BLACK);
                 }));
 
-            let size = iced::Size { width: 100.0, height: 150.0 };
+            let size = iced::Size { width: self.state.card_size_x, height: self.state.card_size_y };
 
-            for i in 0..7 {
+            for i in 0..self.state.tab_cache.len() {
                 geometries.push(self.state.tab_cache[i].draw(renderer, bounds.size(), |frame| {
-                    for (j, index) in self.state.tab_card_indexes[i].iter().enumerate() {
-                        if self.state.cards[*index].visible {
+                    for (j, index) in self.state.game.tab_card_indexes[i].iter().enumerate() {
+                        if self.state.game.cards[*index].visible {
                             frame.draw_image(
                             Rectangle::new(self.state.tab_positions[i][j].0, size),
-                            canvas::Image::new(self.state.cards[*index].image.clone())
+                            canvas::Image::new(self.state.card_image(*index))
                             );
                         } else {
                             frame.draw_image(
@@ -876,71 +1719,99 @@ mod board {
                         }
                     }
                 }));
-                
+
             }
-            
+
             geometries.push(self.state.stock_cache.draw(renderer, bounds.size(), |frame| {
-                if self.state.stock_area_image_index.is_some() {
+                if !self.state.layout.stock {
+                    return
+                }
+                if !self.state.game.stock_card_indexes.is_empty() {
                     frame.draw_image(
                     Rectangle::new(self.state.stock_position.0, size),
                     canvas::Image::new(self.state.cover_image.clone())
                     );
                 } else {
-                    
+
                     let rectangle = Path::rectangle(self.state.stock_position.0, size);
 
                     let style = Style::Solid(Color::WHITE);
 
                     let stroke = Stroke{ style, width: 2.0, ..Default::default()};
-                    
+
                     frame.stroke(&rectangle, stroke);
+
+                    // a cross once the waste can not go back
+                    if !self.state.game.has_passes_left() {
+                        let (top_left, bottom_right) = self.state.stock_position;
+                        let cross = Path::new(|path| {
+                            path.move_to(top_left);
+                            path.line_to(bottom_right);
+                            path.move_to(Point { x: bottom_right.x, y: top_left.y });
+                            path.line_to(Point { x: top_left.x, y: bottom_right.y });
+                        });
+                        frame.stroke(&cross, Stroke{ style: Style::Solid(Color::from_rgb(0.9, 0.2, 0.2)), width: 4.0, ..Default::default()});
+                    }
                 }
             }));
 
             geometries.push(self.state.waste_cache.draw(renderer, bounds.size(), |frame| {
-                let position = iced::Point { x: 170.0, y: 25.0};
-                
-                if self.state.waste_area_image_index.is_some() {
-                    let index = self.state.waste_area_image_index.unwrap();
+                if !self.state.layout.waste {
+                    return
+                }
+                let position = self.state.waste_position.0;
+
+                if let Some(index) = self.state.game.waste_card_indexes.last() {
                     frame.draw_image(
                         Rectangle::new(position, size),
-                        canvas::Image::new(self.state.cards[index].image.clone())
+                        canvas::Image::new(self.state.card_image(*index))
                         );
                 } else {
-                    let size = iced::Size { width: 100.0, height: 150.0 };
                     let rectangle = Path::rectangle(position, size);
 
                     let style = Style::Solid(Color::WHITE);
 
                     let stroke = Stroke{ style, width: 2.0, ..Default::default()};
-                    
+
                     frame.stroke(&rectangle, stroke);
-                }   
+                }
             }));
 
             geometries.push(self.state.foundation_cache.draw(renderer, bounds.size(), |frame| {
-                for (i, index) in self.state.foundation_cards.iter().enumerate() {
-                    if *index < 52 {
-                        frame.draw_image(
-                        Rectangle::new(self.state.foundation_positions[i].0, size),
-                        canvas::Image::new(self.state.cards[self.state.foundation_cards[i]].image.clone())
-                        );
-                    } else {
-                        let size = iced::Size { width: 100.0, height: 150.0 };
-                        let rectangle = Path::rectangle(self.state.foundation_positions[i].0, size);
+                self.draw_piles(frame, &self.state.game.foundation_cards, &self.state.foundation_positions, size);
+            }));
 
-                        let style = Style::Solid(Color::WHITE);
+            geometries.push(self.state.cell_cache.draw(renderer, bounds.size(), |frame| {
+                self.draw_piles(frame, &self.state.game.cell_cards, &self.state.cell_positions, size);
+            }));
 
-                        let stroke = Stroke{ style, width: 2.0, ..Default::default()};
-                        
-                        frame.stroke(&rectangle, stroke);
-                        
+            if let Some((_, mv)) = self.state.hint {
+                let mut frame = Frame::new(renderer, bounds.size());
+                let (source, destination) = self.state.hint_areas(mv);
+                for (area, color) in [(source, Color::from_rgb(1.0, 0.8, 0.0)),
+                                      (destination, Color::from_rgb(0.2, 0.9, 0.3))] {
+                    frame.stroke(&area_rectangle(area), Stroke{ style: Style::Solid(color), width: 4.0, ..Default::default()});
+                }
+                geometries.push(frame.into_geometry());
+            }
+
+            if let Some(area) = self.state.focus_area() {
+                let mut frame = Frame::new(renderer, bounds.size());
+                if let Some(held) = self.state.held {
+                    for target in self.state.drop_targets(held) {
+                        frame.fill(&area_rectangle(target), Color { a: 0.25, ..Color::from_rgb(0.2, 0.9, 0.3) });
+                    }
+                    if let Some(selection) = self.state.selection(held) {
+                        frame.stroke(&area_rectangle(selection),
+                                     Stroke{ style: Style::Solid(Color::from_rgb(0.3, 0.6, 1.0)), width: 4.0, ..Default::default()});
                     }
                 }
-            }));
+                frame.stroke(&area_rectangle(area), Stroke{ style: Style::Solid(Color::WHITE), width: 3.0, ..Default::default()});
+                geometries.push(frame.into_geometry());
+            }
 
             if let Some(pending) = state {
-                geometries.push(pending.draw(renderer, theme, bounds, cursor));
+                geometries.push(pending.draw(self.state, renderer, bounds, cursor));
                 geometries
             } else {
                 geometries
@@ -954,12 +1825,14 @@ mod board {
             cursor: mouse::Cursor,
         ) -> mouse::Interaction {
             if cursor.is_over(bounds) {
-                if state.is_some() {
+                if let Some(Pending::Drag { .. }) = state {
+                    mouse::Interaction::Grabbing
+                } else if state.is_some() {
                     mouse::Interaction::Grab
                 } else {
                     mouse::Interaction::Pointer
                 }
-                
+
             } else {
                 mouse::Interaction::default()
             }
@@ -972,23 +1845,7 @@ mod board {
         }
     }
 
-    
 
-    #[test]
-    fn test_load_cards() {
-        let cards = load_cards();
-
-        // search for any duplicates
-        for (index, card) in cards.iter().enumerate() {
-            for search_index in index+1..cards.len()-1 {
-                if cards[search_index].value == card.value && 
-                    cards[search_index].suite == card.suite {
-                        dbg!(card);
-                }
-            }
-        }
-        dbg!("None found");
-    }
 
     #[test]
     fn test_card_position(){
@@ -1009,130 +1866,320 @@ mod board {
     #[test]
     fn test_move_cards_stock_to_waste() {
         let mut state = State::new();
-        state.start();
+        state.start(None);
 
-        let stock_len = state.stock_card_indexes.len();
-        let waste_len = state.waste_card_indexes.len();
+        let stock_len = state.game.stock_card_indexes.len();
 
-        state.move_stock_to_waste();
+        let from = state.stock_position.0;
+        let to = state.waste_position.0;
+        let areas = state.move_cards(CardPosition { from, to });
 
-        let final_stock_len = state.stock_card_indexes.len();
-        let final_waste_len = state.waste_card_indexes.len();
-        
-        assert_eq!(stock_len-3, final_stock_len);
-        assert_eq!(waste_len+3, final_waste_len);
+        assert_eq!(areas, Ok(vec![Area::Stock, Area::Waste]));
+        assert_eq!(stock_len-3, state.game.stock_card_indexes.len());
     }
 
     #[test]
-    fn test_move_waste_to_stock() {
+    fn test_move_for() {
         let mut state = State::new();
-        state.start();
-
-        let stock_len = state.stock_card_indexes.len();
-        
-        // remove one to have an even number
-        state.stock_card_indexes.remove(3);
-
-        for _ in 0..stock_len/3 {
-            state.move_stock_to_waste();
-        }
+        state.start(None);
 
-        state.move_waste_to_stock();
+        let offset = iced::Vector::new(10.0, 10.0);
 
-        let final_stock_len = state.stock_card_indexes.len();
-        let final_waste_len = state.waste_card_indexes.len();
+        let from = state.tab_positions[3][3].0 + offset;
+        let to = state.foundation_positions[1].0 + offset;
+        assert_eq!(state.move_for(CardPosition { from, to }),
+                    Some(Move::TableauToFoundation { from: 3, to: 1 }));
 
-        assert_eq!(stock_len-1, final_stock_len);
-        assert_eq!(0, final_waste_len);
+        // selecting a card under the top one moves the cards on top of it as well
+        let from = state.tab_positions[5][4].0 + offset;
+        let to = state.tab_positions[2][0].0 + offset;
+        assert_eq!(state.move_for(CardPosition { from, to }),
+                    Some(Move::TableauToTableau { from: 5, depth: 2, to: 2 }));
 
+        let from = state.waste_position.0 + offset;
+        let to = state.stock_position.0 + offset;
+        assert_eq!(state.move_for(CardPosition { from, to }), Some(Move::Recycle));
     }
 
     #[test]
-    fn test_move_waste_to_tab() {
+    fn test_next_hint() {
         let mut state = State::new();
-        state.start();
+        state.start(Some(1));
 
-        // move 3 cards over to waste first
-        state.move_stock_to_waste();
+        let count = solver::ordered_moves(&state.game).len();
+        assert_eq!(Some((0, count)), state.next_hint());
+        let first = state.hint.unwrap().1;
 
-        let top_card_opt = state.waste_card_indexes.last();
+        // pressing again goes through every alternative and back to the first
+        for index in 1..count {
+            assert_eq!(Some((index, count)), state.next_hint());
+        }
+        assert_eq!(Some((0, count)), state.next_hint());
+        assert_eq!(Some(first), state.hint.map(|(_, mv)| mv));
 
-        let top_card = *top_card_opt.unwrap();
+        state.move_cards(CardPosition { from: state.stock_position.0, to: state.waste_position.0 }).unwrap();
+        assert!(state.hint.is_none());
+    }
 
-        let tab_index = 4;
-        let before_last_tab_card_opt = state.tab_card_indexes[tab_index].last();
+    #[test]
+    fn test_send_to_foundation() {
+        let mut state = State::new();
+        state.start(Some(1));
+        state.game.cards[state.game.tab_card_indexes[0][0]].id = CardId::new(Suit::Hearts, Rank::Ace);
+        state.game.cards[state.game.tab_card_indexes[1][1]].id = CardId::new(Suit::Spades, Rank::Two);
 
-        let before_last_tab_card = *before_last_tab_card_opt.unwrap();
+        let offset = iced::Vector::new(10.0, 10.0);
 
-        // setup incoming card for value and color
-        let before_card = state.cards[before_last_tab_card].clone();
-        let value = before_card.value;
-        let color = before_card.color;
+        // the ace starts the first empty foundation
+        let areas = state.send_to_foundation(state.tab_positions[0][0].0 + offset);
+        assert_eq!(areas, Ok(vec![Area::Tableau(0), Area::Foundation(0)]));
 
-        state.cards[top_card].value = value-1;
-        if color == "red".to_string() {
-            state.cards[top_card].color = "black".to_string();
-        } else {
-            state.cards[top_card].color = "red".to_string();
-        }
-        
-        state.move_waste_to_tab(tab_index);
+        // only the top card of a column can go up
+        assert_eq!(state.send_to_foundation(state.tab_positions[1][0].0 + offset), Ok(vec![Area::None]));
+        assert_eq!(state.send_to_foundation(state.tab_positions[1][1].0 + offset), Err(MoveError::NoFoundation));
+    }
 
-        let last_tab_card = state.tab_card_indexes[tab_index].last();
-        let card = *last_tab_card.unwrap();
-        
-        let waste_card = *state.waste_card_indexes.last().unwrap();
+    #[test]
+    fn test_dragged_cards() {
+        let mut state = State::new();
+        state.start(Some(1));
 
-        assert_eq!(top_card, card);
+        let offset = iced::Vector::new(10.0, 10.0);
 
-        assert_ne!(top_card, waste_card);
+        // the face up top card of a column and nothing under it
+        let cards = state.dragged_cards(state.tab_positions[4][4].0 + offset);
+        assert_eq!(cards, vec![(state.game.tab_card_indexes[4][4], state.tab_positions[4][4].0)]);
+        assert!(state.dragged_cards(state.tab_positions[4][3].0 + offset).is_empty());
 
+        // the waste is empty until a card is drawn
+        assert!(state.dragged_cards(state.waste_position.0 + offset).is_empty());
+        state.play(Move::StockToWaste).unwrap();
+        let top = *state.game.waste_card_indexes.last().unwrap();
+        assert_eq!(state.dragged_cards(state.waste_position.0 + offset), vec![(top, state.waste_position.0)]);
+        assert!(state.dragged_cards(state.stock_position.0 + offset).is_empty());
     }
 
     #[test]
-    fn test_move_waste_to_foundation() {
+    fn test_drop_targets() {
         let mut state = State::new();
-        state.start();
+        state.start(Some(1));
+        state.game.cards[state.game.tab_card_indexes[0][0]].id = CardId::new(Suit::Hearts, Rank::Ace);
 
-        // move 3 cards over to waste first
-        state.move_stock_to_waste();
+        let offset = iced::Vector::new(10.0, 10.0);
 
-        let top_card_opt = state.waste_card_indexes.last();
+        // an ace can start any of the foundations
+        let from = state.tab_positions[0][0].0 + offset;
+        let top_left = state.tab_positions[0][0].0;
+        assert_eq!(state.selection(from), Some((top_left, top_left + iced::Vector::new(100.0, 150.0))));
+        assert_eq!(state.drop_targets(from), state.foundation_positions);
 
-        let top_card = *top_card_opt.unwrap();
+        // the stock only goes to the waste
+        let from = state.stock_position.0 + offset;
+        assert_eq!(state.selection(from), Some(state.stock_position));
+        assert_eq!(state.drop_targets(from), vec![state.waste_position]);
 
-        state.move_waste_to_foundation(0);
+        // face down cards can not be selected
+        assert_eq!(state.selection(state.tab_positions[6][0].0 + offset), None);
+    }
 
-        assert_eq!(top_card, state.foundation_cards[0])
+    #[test]
+    fn test_keys() {
+        let mut state = State::new();
+        state.start(Some(1));
+        state.game.cards[state.game.tab_card_indexes[0][0]].id = CardId::new(Suit::Hearts, Rank::Ace);
+
+        // select on the stock draws
+        let positions = state.key(Key::Select).unwrap();
+        assert_eq!(state.move_for(positions), Some(Move::StockToWaste));
+
+        // up from a column goes to the pile above it, right wraps around
+        state.key(Key::Column(3));
+        assert_eq!(state.focused(), Focus::Tableau(3, 3));
+        state.key(Key::Up);
+        assert_eq!(state.focused(), Focus::Top(2));
+        state.key(Key::Left);
+        state.key(Key::Down);
+        assert_eq!(state.focused(), Focus::Tableau(1, 1));
+        state.key(Key::Column(6));
+        state.key(Key::Right);
+        assert_eq!(state.focused(), Focus::Tableau(0, 0));
+
+        // pick up the ace and drop it on a foundation
+        assert!(state.key(Key::Select).is_none());
+        state.key(Key::Up);
+        state.key(Key::Right);
+        state.key(Key::Right);
+        let positions = state.key(Key::Select).unwrap();
+        assert_eq!(state.move_for(positions), Some(Move::TableauToFoundation { from: 0, to: 0 }));
+        assert_eq!(state.held, None);
+
+        // cancel lets go of the cards
+        state.key(Key::Column(2));
+        state.key(Key::Select);
+        assert!(state.held.is_some());
+        state.key(Key::Cancel);
+        assert_eq!(state.held, None);
+
+        // a new deal lets go of the focus
+        state.key(Key::Column(6));
+        state.start(Some(2));
+        assert_eq!(state.focus_area(), None);
     }
 
     #[test]
-    fn test_move_tab_to_tab() {
+    fn test_keys_after_variant_change() {
         let mut state = State::new();
-        state.start();
+        state.variant = VariantKind::FreeCell;
+        state.start(Some(1));
+
+        // the last column exists only in FreeCell
+        state.key(Key::Column(7));
+        state.variant = VariantKind::Klondike;
+        state.start(Some(1));
+        assert_eq!(state.focus_area(), None);
+        // the focus starts over on the stock
+        assert!(state.key(Key::Select).is_some());
+
+        // and the tenth column only in Spider
+        state.variant = VariantKind::Spider(Suits::One);
+        state.start(Some(1));
+        state.key(Key::Column(9));
+        state.variant = VariantKind::Klondike;
+        state.start(Some(1));
+        assert_eq!(state.focus_area(), None);
+        assert!(state.key(Key::Select).is_some());
+
+        // Pyramid has a single foundation
+        state.variant = VariantKind::Spider(Suits::One);
+        state.start(Some(1));
+        state.key(Key::Up);
+        state.key(Key::Left);
+        state.variant = VariantKind::Pyramid;
+        state.start(Some(1));
+        assert_eq!(state.focus_area(), None);
+        assert!(state.key(Key::Select).is_some());
+    }
 
-        let tab_index_from = 3;
-        let tab_index_to = 4;
+    #[test]
+    fn test_free_cells() {
+        let mut state = State::new();
+        state.variant = VariantKind::FreeCell;
+        state.start(Some(1));
+
+        let offset = iced::Vector::new(10.0, 10.0);
+        assert_eq!(8, state.tab_positions.len());
+        assert_eq!(4, state.cell_positions.len());
+        assert!(state.cell_positions[3].1.x < state.foundation_positions[0].0.x);
+
+        // the top card goes into a cell and can be picked up from there
+        let from = state.tab_positions[0][6].0 + offset;
+        let to = state.cell_positions[2].0 + offset;
+        assert_eq!(state.move_for(CardPosition { from, to }), Some(Move::TableauToCell { from: 0, to: 2 }));
+        let areas = state.move_cards(CardPosition { from, to });
+        assert_eq!(areas, Ok(vec![Area::Tableau(0), Area::Cell(2)]));
+        let parked = state.game.cell_cards[2].unwrap();
+        assert_eq!(state.dragged_cards(to), vec![(parked, state.cell_positions[2].0)]);
+
+        // only the top card fits in a cell
+        let from = state.tab_positions[1][5].0 + offset;
+        let to = state.cell_positions[0].0 + offset;
+        assert_eq!(state.move_for(CardPosition { from, to }), None);
+    }
 
-        let before_len_from = state.tab_card_indexes[3].len();
-        let before_len_to = state.tab_card_indexes[4].len();
+    #[test]
+    fn test_deal_row() {
+        let mut state = State::new();
+        state.variant = VariantKind::Spider(Suits::Four);
+        state.start(Some(1));
+
+        // a press on the stock deals without picking a destination
+        let point = state.stock_position.0 + iced::Vector::new(10.0, 10.0);
+        assert_eq!(state.move_for(CardPosition { from: point, to: point }), Some(Move::DealRow));
+        assert_eq!(state.stock_move(), Move::DealRow);
+
+        let areas = state.move_cards(CardPosition { from: point, to: point }).unwrap();
+        assert_eq!(11, areas.len());
+        assert_eq!(40, state.game.stock_card_indexes.len());
+        assert!(state.game.tab_card_indexes.iter().all(|tab| tab.len() >= 6));
+    }
 
-        state.move_tab_to_tab((tab_index_from, before_len_from-1), (tab_index_to, before_len_to-1));
+    #[test]
+    fn test_pyramid() {
+        let mut state = State::new();
+        state.variant = VariantKind::Pyramid;
+        state.start(Some(1));
+
+        let offset = iced::Vector::new(10.0, 10.0);
+        assert_eq!(28, state.tab_positions.len());
+        assert!(state.waste_position.1.x < state.foundation_positions[0].0.x);
+
+        // only the top of a card shows above the row below
+        let top = state.tab_positions[0][0].0;
+        assert_eq!(state.find_tab_area(top + offset), (Some(0), Some(0)));
+        let covered = Point { x: top.x + 10.0, y: state.tab_positions[1][0].0.y + 10.0 };
+        assert_eq!(state.find_tab_area(covered), (Some(1), Some(0)));
+
+        // cards pair up with each other and with the waste
+        let from = state.tab_positions[27][0].0 + offset;
+        let to = state.tab_positions[26][0].0 + offset;
+        let waste = state.waste_position.0 + offset;
+        assert_eq!(state.move_for(CardPosition { from, to }), Some(Move::TableauPair { from: 27, with: 26 }));
+        assert_eq!(state.move_for(CardPosition { from: waste, to: from }), Some(Move::WastePair(27)));
+        assert_eq!(state.move_for(CardPosition { from, to: waste }), Some(Move::WastePair(27)));
+
+        let rank = |rank| state.game.cards.iter().position(|card| card.rank() == rank).unwrap();
+        let (six, seven) = (rank(Rank::Six), rank(Rank::Seven));
+        state.game.tab_card_indexes[26] = vec![six];
+        state.game.tab_card_indexes[27] = vec![seven];
+        let areas = state.move_cards(CardPosition { from, to });
+        assert_eq!(areas, Ok(vec![Area::Tableau(27), Area::Tableau(26), Area::Foundation(0)]));
+        assert_eq!(Some(six), state.game.foundation_cards[0]);
+
+        // the card above shows through where the pair was
+        assert_eq!(state.find_tab_area(from), (Some(20), Some(0)));
+    }
 
-        let _after_len_from = state.tab_card_indexes[3].len();
-        let after_len_to = state.tab_card_indexes[4].len();
+    #[test]
+    fn test_undo_to_choice() {
+        let mut state = State::new();
+        state.start(Some(1));
+
+        // either king can go up, after that only the other one and then the
+        // two of clubs is stuck under the three
+        let index = |suit, rank| CardId::new(suit, rank).index();
+        let game = &mut state.game;
+        game.cards = crate::card::deck();
+        game.stock_card_indexes = vec![index(Suit::Clubs, Rank::Four)];
+        game.foundation_cards = vec![Some(index(Suit::Clubs, Rank::Ace)), Some(index(Suit::Spades, Rank::Queen)),
+                                     Some(index(Suit::Hearts, Rank::Queen)), Some(index(Suit::Diamonds, Rank::King))];
+        game.tab_card_indexes = vec![vec![index(Suit::Clubs, Rank::Two), index(Suit::Clubs, Rank::Three)],
+                                     vec![index(Suit::Spades, Rank::King)], vec![index(Suit::Hearts, Rank::King)],
+                                     vec![], vec![], vec![], vec![]];
+        for card in [index(Suit::Clubs, Rank::Three), index(Suit::Spades, Rank::King), index(Suit::Hearts, Rank::King)] {
+            game.cards[card].visible = true;
+        }
 
-        assert_eq!(before_len_from-1, 3);
-        assert_eq!(before_len_to+1, after_len_to)
+        state.play(Move::StockToWaste).unwrap();
+        assert_eq!(2, state.game.productive_moves().len());
+        state.play(Move::TableauToFoundation { from: 1, to: 1 }).unwrap();
+        state.play(Move::TableauToFoundation { from: 2, to: 2 }).unwrap();
+        assert!(state.dead);
+
+        // back to both kings on the board, the draw before them stays
+        state.undo_to_choice();
+        assert!(!state.dead);
+        assert_eq!(Some(index(Suit::Spades, Rank::Queen)), state.game.foundation_cards[1]);
+        assert_eq!(vec![index(Suit::Clubs, Rank::Four)], state.game.waste_card_indexes);
+        assert!(state.history.can_undo());
     }
-    
+
     #[test]
     fn test_tab_positions() {
         let mut state = State::new();
-        state.start();
+        state.start(None);
 
         for (i, tab) in state.tab_positions.iter().enumerate() {
-            println!("{}, {:?}", i, tab);
+            assert_eq!(i+1, tab.len());
         }
 
     }
@@ -1155,16 +2202,16 @@ mod board {
     #[test]
     fn test_find_tab_area() {
         let mut state = State::new();
-        state.start();
+        state.start(None);
 
         let mut point = state.tab_positions[5][2].0;
 
         // make a little offset
         point.x += 10.0;
         point.y += 10.0;
-        
+
         let (tab_index_opt, tab_card_index_opt) = state.find_tab_area(point);
-        
+
         assert_eq!(tab_index_opt, Some(5));
         assert_eq!(tab_card_index_opt, Some(2));
     }
@@ -1172,7 +2219,7 @@ mod board {
     #[test]
     fn test_find_foundation_area() {
         let mut state = State::new();
-        state.start();
+        state.start(None);
 
         for i in 0..4 {
             let mut point = state.foundation_positions[i].0;
@@ -1181,29 +2228,22 @@ mod board {
             point.y += 10.0;
 
             let results = state.find_foundation_area(point);
-  
+
             assert_eq!(results, Some(i));
         }
     }
 
-    // #[test]
-    // fn test_find_card_in_tab() {
-    //     let mut state = State::new();
-    //     state.start();
-
-    //     let mut tab_point = state.tab_positions[6][3].0;
-
-    //     // make a little offset
-    //     tab_point.x += 10.0;
-    //     tab_point.y += 10.0;
-
-    //     let card_index = state.find_card_in_tab(6, tab_point).unwrap();
+}
 
-    //     let actual_card_index = state.tab_card_indexes[6][3];
+#[test]
+fn test_key_message() {
+    use keyboard::Modifiers;
 
-    //     assert_eq!(&state.cards[actual_card_index].value, &state.cards[card_index].value);
-    //     assert_eq!(&state.cards[actual_card_index]._suite, &state.cards[card_index]._suite);
-    // }
+    let key = |letter: &str, modifiers| key_message(Key::Character(letter.into()), modifiers);
 
+    assert!(matches!(key("z", Modifiers::COMMAND), Some(Message::Undo)));
+    assert!(matches!(key("Z", Modifiers::COMMAND | Modifiers::SHIFT), Some(Message::Redo)));
+    assert!(matches!(key("n", Modifiers::empty()), Some(Message::Start)));
+    assert!(key("n", Modifiers::COMMAND).is_none());
+    assert!(key("d", Modifiers::ALT).is_none());
 }
-
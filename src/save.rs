@@ -0,0 +1,168 @@
+//! Saving and resuming a game in progress
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::clock::Clock;
+use crate::game::{Game, History};
+#[cfg(test)]
+use crate::variant::VariantKind;
+
+/// Bumped whenever the saved layout changes, older files are refused.
+pub const SAVE_VERSION: u32 = 6;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedGame {
+    pub version: u32,
+    pub game: Game,
+    pub history: History,
+    pub clock: Clock,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    NoDataDir,
+    Io(io::Error),
+    Format(serde_json::Error),
+    Version(u32),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::NoDataDir => write!(f, "No data directory to save in"),
+            SaveError::Io(error) => write!(f, "Save file error: {}", error),
+            SaveError::Format(error) => write!(f, "Save file is damaged: {}", error),
+            SaveError::Version(version) => write!(f, "Save file version {} is not supported", version),
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(error: io::Error) -> Self {
+        SaveError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(error: serde_json::Error) -> Self {
+        SaveError::Format(error)
+    }
+}
+
+impl SavedGame {
+    pub fn new(game: &Game, history: &History, clock: Clock) -> SavedGame {
+        SavedGame {
+            version: SAVE_VERSION,
+            game: game.clone(),
+            history: history.clone(),
+            clock,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, SaveError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<SavedGame, SaveError> {
+        // check the version before the layout so an old file gets a clear message
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+        let Version { version } = serde_json::from_str(json)?;
+        if version != SAVE_VERSION {
+            return Err(SaveError::Version(version))
+        }
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), SaveError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// Returns None when there is no saved game.
+    pub fn load_from(path: &Path) -> Result<Option<SavedGame>, SaveError> {
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(Some(SavedGame::from_json(&json)?)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+/// Folder in the user's data directory for everything the game writes.
+pub fn data_dir() -> Result<PathBuf, SaveError> {
+    dirs::data_dir()
+        .map(|dir| dir.join("rust_iced_solitaire"))
+        .ok_or(SaveError::NoDataDir)
+}
+
+pub fn save(game: &Game, history: &History, clock: Clock) -> Result<(), SaveError> {
+    SavedGame::new(game, history, clock).save_to(&data_dir()?.join("saved_game.json"))
+}
+
+pub fn load() -> Result<Option<SavedGame>, SaveError> {
+    SavedGame::load_from(&data_dir()?.join("saved_game.json"))
+}
+
+/// Deletes the saved game, nothing to do when there is none.
+pub fn remove() -> Result<(), SaveError> {
+    match fs::remove_file(data_dir()?.join("saved_game.json")) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error.into()),
+        _ => Ok(()),
+    }
+}
+
+#[test]
+fn test_save_round_trip() {
+    use crate::game::Move;
+
+    let mut game = Game::deal(VariantKind::Klondike, 42, 1);
+    let mut history = History::default();
+    history.record(game.clone());
+    game.apply(Move::StockToWaste).unwrap();
+
+    let json = SavedGame::new(&game, &history, Clock::default()).to_json().unwrap();
+    let saved = SavedGame::from_json(&json).unwrap();
+
+    assert_eq!(42, saved.game.deal);
+    assert_eq!(1, saved.game.cards_to_play);
+    assert_eq!(game.waste_card_indexes, saved.game.waste_card_indexes);
+    assert_eq!(game.tab_card_indexes, saved.game.tab_card_indexes);
+    let visible = |game: &Game| game.cards.iter().map(|card| card.visible).collect::<Vec<_>>();
+    assert_eq!(visible(&game), visible(&saved.game));
+
+    let mut resumed = saved.game;
+    let mut history = saved.history;
+    assert!(history.undo(&mut resumed));
+    assert!(resumed.waste_card_indexes.is_empty());
+}
+
+#[test]
+fn test_save_version() {
+    let json = SavedGame::new(&Game::deal(VariantKind::Klondike, 1, 3), &History::default(), Clock::default()).to_json().unwrap();
+    let json = json.replace(&format!("\"version\":{}", SAVE_VERSION), "\"version\":0");
+
+    assert!(matches!(SavedGame::from_json(&json), Err(SaveError::Version(0))));
+}
+
+#[test]
+fn test_save_to_file() {
+    let path = std::env::temp_dir().join(format!("solitaire_save_{}", std::process::id())).join("saved_game.json");
+
+    assert!(SavedGame::load_from(&path).unwrap().is_none());
+
+    SavedGame::new(&Game::deal(VariantKind::Klondike, 7, 3), &History::default(), Clock::default()).save_to(&path).unwrap();
+    let saved = SavedGame::load_from(&path).unwrap().unwrap();
+    assert_eq!(7, saved.game.deal);
+
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
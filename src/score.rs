@@ -0,0 +1,141 @@
+//! Scoring rules
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::Move;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scoring {
+    /// Points for building and turning cards over, never below zero.
+    #[default]
+    Standard,
+    /// The deck is bought for a dollar a card and each card on a
+    /// foundation pays five back.
+    Vegas,
+}
+
+impl Scoring {
+    pub const ALL: [Scoring; 2] = [Scoring::Standard, Scoring::Vegas];
+
+    /// Score a new game with that many cards starts with, in Vegas each
+    /// card is bought for a dollar.
+    pub fn opening(self, deck_size: usize) -> i32 {
+        match self {
+            Scoring::Standard => 0,
+            Scoring::Vegas => -(deck_size as i32),
+        }
+    }
+
+    /// Points for a move, `flipped` when the move turned a tableau card over.
+    pub fn points(self, mv: Move, flipped: bool, cards_to_play: usize) -> i32 {
+        match self {
+            Scoring::Standard => {
+                let points = match mv {
+                    Move::WasteToTableau(_) => 5,
+                    Move::WasteToFoundation(_) | Move::TableauToFoundation { .. }
+                        | Move::CellToFoundation { .. } => 10,
+                    // a pair is two cards gone, each scores
+                    Move::TableauPair { .. } | Move::WastePair(_) => 20,
+                    Move::FoundationToTableau { .. } => -15,
+                    Move::Recycle if cards_to_play == 1 => -100,
+                    Move::Recycle => -20,
+                    Move::StockToWaste | Move::DealRow | Move::TableauToTableau { .. }
+                        | Move::TableauToCell { .. } | Move::CellToTableau { .. } => 0,
+                };
+                if flipped { points + 5 } else { points }
+            },
+            Scoring::Vegas => match mv {
+                Move::WasteToFoundation(_) | Move::TableauToFoundation { .. } | Move::CellToFoundation { .. } => 5,
+                Move::TableauPair { .. } | Move::WastePair(_) => 10,
+                Move::FoundationToTableau { .. } => -5,
+                _ => 0,
+            },
+        }
+    }
+
+    /// Points for cards the rules took off to the foundations by themselves,
+    /// each one pays as if it was moved there.
+    pub fn foundation_points(self, cards: usize) -> i32 {
+        let per_card = match self {
+            Scoring::Standard => 10,
+            Scoring::Vegas => 5,
+        };
+        per_card * cards as i32
+    }
+
+    /// Adds points to a score, a standard score stops at zero.
+    pub fn add(self, score: i32, points: i32) -> i32 {
+        match self {
+            Scoring::Standard => (score + points).max(0),
+            Scoring::Vegas => score + points,
+        }
+    }
+
+    /// The score as shown in the sidebar.
+    pub fn format(self, score: i32) -> String {
+        match self {
+            Scoring::Standard => score.to_string(),
+            Scoring::Vegas if score < 0 => format!("-${}", -score),
+            Scoring::Vegas => format!("${}", score),
+        }
+    }
+}
+
+impl fmt::Display for Scoring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scoring::Standard => write!(f, "Standard"),
+            Scoring::Vegas => write!(f, "Vegas"),
+        }
+    }
+}
+
+#[test]
+fn test_standard_scoring() {
+    let scoring = Scoring::Standard;
+
+    assert_eq!(5, scoring.points(Move::WasteToTableau(0), false, 3));
+    assert_eq!(15, scoring.points(Move::TableauToFoundation { from: 0, to: 0 }, true, 3));
+    assert_eq!(5, scoring.points(Move::TableauToTableau { from: 0, depth: 1, to: 1 }, true, 3));
+    assert_eq!(-100, scoring.points(Move::Recycle, false, 1));
+    assert_eq!(-20, scoring.points(Move::Recycle, false, 3));
+    assert_eq!(130, scoring.foundation_points(13));
+    assert_eq!(20, scoring.points(Move::TableauPair { from: 0, with: 1 }, false, 1));
+
+    assert_eq!(0, scoring.add(10, -15));
+}
+
+#[test]
+fn test_vegas_scoring() {
+    let scoring = Scoring::Vegas;
+
+    assert_eq!(-52, scoring.opening(52));
+    assert_eq!(5, scoring.points(Move::WasteToFoundation(0), true, 3));
+    assert_eq!(-5, scoring.points(Move::FoundationToTableau { from: 0, to: 0 }, false, 3));
+    assert_eq!(0, scoring.points(Move::Recycle, false, 1));
+    assert_eq!(65, scoring.foundation_points(13));
+    assert_eq!(10, scoring.points(Move::WastePair(0), false, 1));
+
+    assert_eq!(-57, scoring.add(-52, -5));
+    assert_eq!("-$57", scoring.format(-57));
+    assert_eq!("$8", scoring.format(8));
+}
+
+#[test]
+fn test_game_score() {
+    use crate::card::deck;
+    use crate::game::Game;
+    use crate::variant::VariantKind;
+
+    let mut game = Game::new(VariantKind::Klondike, deck(), 3).with_scoring(Scoring::Vegas);
+    assert_eq!(-52, game.score);
+
+    // the first column holds the ace of clubs
+    game.apply(Move::TableauToFoundation { from: 0, to: 0 }).unwrap();
+    assert_eq!(-47, game.score);
+
+    let mut game = Game::new(VariantKind::Klondike, deck(), 3);
+    game.apply(Move::TableauToFoundation { from: 0, to: 0 }).unwrap();
+    assert_eq!(10, game.score);
+}
@@ -0,0 +1,354 @@
+//! Solver
+//!
+//! Searches the lines of play from a position for a win. The engine knows
+//! the hidden tableau cards and the stock order, so the search plays with
+//! every card known and the answer is for the position as it lies, not for
+//! the deal it came from.
+use std::collections::HashSet;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::{Duration, Instant};
+
+use crate::card::Card;
+use crate::game::{Game, Move};
+
+/// How much searching is allowed before giving up.
+#[derive(Clone, Copy, Debug)]
+pub struct Budget {
+    pub nodes: usize,
+    pub time: Duration,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Budget {
+            nodes: 500_000,
+            time: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Solution {
+    /// Moves that win from the position.
+    Winnable(Vec<Move>),
+    /// Every line was searched without a win.
+    Unwinnable,
+    /// The budget ran out before an answer was found.
+    Unknown,
+}
+
+struct Frame {
+    game: Game,
+    moves: Vec<Move>,
+    next: usize,
+    // length of the winning line up to this position
+    path_len: usize,
+}
+
+enum Node {
+    Won,
+    Seen,
+    Open(Box<Frame>),
+}
+
+/// Depth first search for a winning line, positions already searched are
+/// skipped so waste cycles and column shuffles are only tried once.
+pub fn solve(game: &Game, budget: Budget) -> Solution {
+    let started = Instant::now();
+    let mut seen = HashSet::new();
+    let mut path = vec![];
+    let mut stack = vec![];
+
+    match enter(game.clone(), &mut path, &mut seen) {
+        Node::Won => return Solution::Winnable(path),
+        Node::Seen => return Solution::Unwinnable,
+        Node::Open(frame) => stack.push(*frame),
+    }
+
+    let mut steps: usize = 0;
+    while let Some(frame) = stack.last_mut() {
+        steps += 1;
+        if seen.len() > budget.nodes || (steps.is_multiple_of(1024) && started.elapsed() > budget.time) {
+            return Solution::Unknown
+        }
+
+        let Some(&mv) = frame.moves.get(frame.next) else {
+            stack.pop();
+            continue
+        };
+        frame.next += 1;
+
+        path.truncate(frame.path_len);
+        let mut next = frame.game.clone();
+        if next.apply(mv).is_err() {
+            continue
+        }
+        path.push(mv);
+
+        match enter(next, &mut path, &mut seen) {
+            Node::Won => return Solution::Winnable(path),
+            Node::Seen => (),
+            Node::Open(child) => stack.push(*child),
+        }
+    }
+
+    Solution::Unwinnable
+}
+
+// plays the safe foundation moves, which never need to be searched, then
+// opens the position unless it was searched before
+fn enter(mut game: Game, path: &mut Vec<Move>, seen: &mut HashSet<u64>) -> Node {
+    while let Some(mv) = safe_foundation_move(&game) {
+        if game.apply(mv).is_err() {
+            break
+        }
+        path.push(mv);
+    }
+
+    if game.is_won() {
+        return Node::Won
+    }
+    if !seen.insert(key(&game)) {
+        return Node::Seen
+    }
+
+    Node::Open(Box::new(Frame {
+        moves: ordered_moves(&game),
+        next: 0,
+        path_len: path.len(),
+        game,
+    }))
+}
+
+/// Legal moves with the most promising first, pointless shuffles of a
+/// whole column into an empty one are left out.
+pub fn ordered_moves(game: &Game) -> Vec<Move> {
+    let mut moves: Vec<(u8, Move)> = game.legal_moves().into_iter()
+        .filter_map(|mv| priority(game, mv).map(|priority| (priority, mv)))
+        .collect();
+    moves.sort_by_key(|(priority, _)| *priority);
+    moves.into_iter().map(|(_, mv)| mv).collect()
+}
+
+// lower is tried first, None for moves that can never help. Taking cards
+// back off the foundations is rarely needed but still tried last, or an
+// exhausted search would not prove anything
+fn priority(game: &Game, mv: Move) -> Option<u8> {
+    match mv {
+        Move::FoundationToTableau { .. } => Some(5),
+        Move::WasteToFoundation(_) | Move::TableauToFoundation { .. } | Move::CellToFoundation { .. }
+            | Move::TableauPair { .. } | Move::WastePair(_) => Some(0),
+        Move::TableauToTableau { from, depth, to } => {
+            let tab = &game.tab_card_indexes[from];
+            let remaining = tab.len() - depth;
+            if remaining == 0 {
+                if game.tab_card_indexes[to].is_empty() {
+                    return None
+                }
+                // empties a column
+                return Some(1)
+            }
+            if !game.cards[tab[remaining - 1]].visible {
+                // turns a card over
+                return Some(1)
+            }
+            Some(3)
+        },
+        Move::WasteToTableau(_) | Move::CellToTableau { .. } => Some(2),
+        Move::StockToWaste | Move::Recycle | Move::DealRow | Move::TableauToCell { .. } => Some(4),
+    }
+}
+
+// a card can go up without losing anything once the cards that could be
+// built on it are either up already or not needed, twos are always safe
+fn safe_foundation_move(game: &Game) -> Option<Move> {
+    let level = |card: &Card, same_color: bool| {
+        game.foundation_cards.iter()
+            .filter_map(|fd| fd.map(|index| &game.cards[index]))
+            .filter(|top| top.suit() != card.suit() && (top.color() == card.color()) == same_color)
+            .map(|top| top.rank().value())
+            .collect::<Vec<_>>()
+    };
+    let is_safe = |card: &Card| {
+        let rank = card.rank().value();
+        if rank <= 2 {
+            return true
+        }
+        let opposite = level(card, false);
+        let same = level(card, true);
+        opposite.len() == 2 && opposite.iter().all(|value| *value + 1 >= rank)
+            && same.len() == 1 && same[0] + 2 >= rank
+    };
+
+    // each card that could go up with the moves sending it there
+    let foundations = 0..game.foundation_cards.len();
+    let mut moves: Vec<(usize, Move)> = vec![];
+    if let Some(&index) = game.waste_card_indexes.last() {
+        moves.extend(foundations.clone().map(|to| (index, Move::WasteToFoundation(to))));
+    }
+    for (from, tab) in game.tab_card_indexes.iter().enumerate() {
+        if let Some(&index) = tab.last() {
+            moves.extend(foundations.clone().map(|to| (index, Move::TableauToFoundation { from, to })));
+        }
+    }
+    for (from, cell) in game.cell_cards.iter().enumerate() {
+        if let Some(index) = *cell {
+            moves.extend(foundations.clone().map(|to| (index, Move::CellToFoundation { from, to })));
+        }
+    }
+
+    moves.into_iter()
+        .find(|(card_index, mv)| is_safe(&game.cards[*card_index]) && game.check(*mv).is_ok())
+        .map(|(_, mv)| mv)
+}
+
+/// Positions that differ only in the order of the columns, foundations or
+/// cells play the same, so they share a key.
+pub fn key(game: &Game) -> u64 {
+    const SEPARATOR: u8 = u8::MAX;
+
+    let mut columns: Vec<Vec<u8>> = game.tab_card_indexes.iter()
+        .map(|tab| {
+            let face_down = tab.iter().take_while(|index| !game.cards[**index].visible).count();
+            let mut column = vec![face_down as u8];
+            column.extend(tab.iter().map(|index| *index as u8));
+            column
+        })
+        .collect();
+    columns.sort();
+
+    let mut foundations: Vec<u8> = game.foundation_cards.iter()
+        .map(|fd| fd.map_or(SEPARATOR, |index| index as u8))
+        .collect();
+    foundations.sort();
+
+    let mut cells: Vec<u8> = game.cell_cards.iter()
+        .map(|cell| cell.map_or(SEPARATOR, |index| index as u8))
+        .collect();
+    cells.sort();
+
+    let mut hasher = DefaultHasher::new();
+    columns.hash(&mut hasher);
+    foundations.hash(&mut hasher);
+    cells.hash(&mut hasher);
+    game.stock_card_indexes.hash(&mut hasher);
+    game.waste_card_indexes.hash(&mut hasher);
+    // with a pass limit the same piles with fewer passes left play worse
+    if game.passes.limit().is_some() {
+        game.card_rounds.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+use crate::card::{deck, CardId, Rank, Suit};
+#[cfg(test)]
+use crate::variant::VariantKind;
+
+// builds a position from the unshuffled deck, every card not placed is on
+// a foundation below the given tops
+#[cfg(test)]
+fn position(tableau: Vec<Vec<(CardId, bool)>>, stock: Vec<CardId>, foundations: Vec<Option<CardId>>) -> Game {
+    let mut game = Game::new(VariantKind::Klondike, deck(), 1);
+    let index = |id: CardId| id.index();
+
+    game.tab_card_indexes = tableau.iter()
+        .map(|tab| tab.iter().map(|(id, _)| index(*id)).collect())
+        .collect();
+    for (id, visible) in tableau.iter().flatten() {
+        game.cards[index(*id)].visible = *visible;
+    }
+    game.stock_card_indexes = stock.into_iter().map(index).collect();
+    game.waste_card_indexes = vec![];
+    game.foundation_cards = foundations.into_iter().map(|fd| fd.map(index)).collect();
+    game
+}
+
+#[test]
+fn test_solve_nearly_won() {
+    let king = |suit| CardId::new(suit, Rank::King);
+    let queen = |suit| CardId::new(suit, Rank::Queen);
+
+    // the queen of hearts is under the king of spades
+    let game = position(
+        vec![vec![(queen(Suit::Hearts), false), (king(Suit::Spades), true)],
+                vec![(king(Suit::Hearts), true)], vec![], vec![], vec![], vec![], vec![]],
+        vec![king(Suit::Clubs), king(Suit::Diamonds)],
+        vec![Some(queen(Suit::Clubs)), Some(queen(Suit::Spades)), Some(CardId::new(Suit::Hearts, Rank::Jack)), Some(queen(Suit::Diamonds))],
+    );
+
+    let Solution::Winnable(moves) = solve(&game, Budget::default()) else {
+        panic!("should be winnable");
+    };
+
+    let mut replay = game.clone();
+    for mv in moves {
+        replay.apply(mv).unwrap();
+    }
+    assert!(replay.is_won());
+}
+
+#[test]
+fn test_solve_unwinnable() {
+    let clubs = |rank| CardId::new(Suit::Clubs, rank);
+
+    // the two of clubs is buried under the three, the other clubs fill
+    // every column so no king can come down for red cards to build on
+    let mut tableau = vec![vec![(clubs(Rank::Two), false), (clubs(Rank::Three), true)]];
+    tableau.extend(Rank::ALL[3..9].iter().map(|rank| vec![(clubs(*rank), true)]));
+    let mut stock: Vec<CardId> = Rank::ALL[9..].iter().map(|rank| clubs(*rank)).collect();
+    stock.reverse();
+    let game = position(
+        tableau,
+        stock,
+        vec![Some(clubs(Rank::Ace)),
+                Some(CardId::new(Suit::Spades, Rank::King)),
+                Some(CardId::new(Suit::Hearts, Rank::King)),
+                Some(CardId::new(Suit::Diamonds, Rank::King))],
+    );
+
+    assert_eq!(Solution::Unwinnable, solve(&game, Budget::default()));
+}
+
+#[test]
+fn test_solve_budget() {
+    let game = Game::deal(VariantKind::Klondike, 1, 3);
+    let budget = Budget { nodes: 10, time: Duration::from_secs(5) };
+
+    assert_eq!(Solution::Unknown, solve(&game, budget));
+}
+
+#[test]
+fn test_solve_deal() {
+    let budget = Budget { nodes: 10_000, time: Duration::from_secs(5) };
+    let game = Game::deal(VariantKind::Klondike, 2, 1);
+
+    let Solution::Winnable(moves) = solve(&game, budget) else {
+        panic!("draw one deal 2 should be winnable");
+    };
+    let mut replay = game.clone();
+    for mv in moves {
+        replay.apply(mv).unwrap();
+    }
+    assert!(replay.is_won());
+}
+
+#[test]
+fn test_solve_foundation_to_tableau() {
+    let id = CardId::new;
+
+    // only winnable by taking the six of spades down for the five of hearts,
+    // so the four under it can be turned over
+    let mut stock = vec![id(Suit::Hearts, Rank::Six)];
+    stock.extend(Rank::ALL[7..].iter().map(|rank| id(Suit::Hearts, *rank)));
+    stock.extend(Rank::ALL[6..].iter().map(|rank| id(Suit::Spades, *rank)));
+    let game = position(
+        vec![vec![(id(Suit::Hearts, Rank::Four), false), (id(Suit::Hearts, Rank::Five), true)],
+                vec![(id(Suit::Hearts, Rank::Seven), true)], vec![], vec![], vec![], vec![], vec![]],
+        stock,
+        vec![Some(id(Suit::Spades, Rank::Six)), Some(id(Suit::Clubs, Rank::King)),
+                Some(id(Suit::Hearts, Rank::Three)), Some(id(Suit::Diamonds, Rank::King))],
+    );
+
+    assert!(matches!(solve(&game, Budget::default()), Solution::Winnable(_)));
+}
@@ -0,0 +1,176 @@
+//! Player statistics kept between sessions
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::save::{data_dir, SaveError};
+use crate::score::Scoring;
+use crate::variant::VariantKind;
+
+/// Results for one game, or one draw mode of it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub played: u32,
+    pub won: u32,
+    pub streak: u32,
+    pub longest_streak: u32,
+    pub best_time: Option<Duration>,
+    pub fewest_moves: Option<u32>,
+    /// Best standard score, Vegas winnings are kept apart in `best_vegas`.
+    pub best_score: Option<i32>,
+    pub best_vegas: Option<i32>,
+}
+
+impl Record {
+    pub fn win(&mut self, time: Duration, moves: u32, scoring: Scoring, score: i32) {
+        self.played += 1;
+        self.won += 1;
+        self.streak += 1;
+        self.longest_streak = self.longest_streak.max(self.streak);
+        self.best_time = Some(self.best_time.map_or(time, |best| best.min(time)));
+        self.fewest_moves = Some(self.fewest_moves.map_or(moves, |best| best.min(moves)));
+        let best = match scoring {
+            Scoring::Standard => &mut self.best_score,
+            Scoring::Vegas => &mut self.best_vegas,
+        };
+        *best = Some(best.map_or(score, |best| best.max(score)));
+    }
+
+    pub fn loss(&mut self) {
+        self.played += 1;
+        self.streak = 0;
+    }
+
+    pub fn win_percentage(&self) -> f32 {
+        if self.played == 0 {
+            return 0.0
+        }
+        self.won as f32 * 100.0 / self.played as f32
+    }
+}
+
+static NOT_PLAYED: Record = Record {
+    played: 0,
+    won: 0,
+    streak: 0,
+    longest_streak: 0,
+    best_time: None,
+    fewest_moves: None,
+    best_score: None,
+    best_vegas: None,
+};
+
+/// Which record a game goes into, the games with a waste are kept apart by
+/// how many cards they draw at a time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordKey {
+    pub variant: VariantKind,
+    pub draw: Option<usize>,
+}
+
+impl RecordKey {
+    pub fn new(variant: VariantKind, cards_to_play: usize) -> RecordKey {
+        let draw = variant.rules().layout().waste.then_some(if cards_to_play == 1 { 1 } else { 3 });
+        RecordKey { variant, draw }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Statistics {
+    records: Vec<(RecordKey, Record)>,
+    /// Vegas winnings carried from one game to the next.
+    pub bankroll: i32,
+}
+
+impl Statistics {
+    pub fn record(&self, key: RecordKey) -> &Record {
+        self.records.iter()
+            .find(|(other, _)| *other == key)
+            .map_or(&NOT_PLAYED, |(_, record)| record)
+    }
+
+    pub fn record_mut(&mut self, key: RecordKey) -> &mut Record {
+        let index = match self.records.iter().position(|(other, _)| *other == key) {
+            Some(index) => index,
+            None => {
+                self.records.push((key, Record::default()));
+                self.records.len() - 1
+            },
+        };
+        &mut self.records[index].1
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), SaveError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Empty statistics when nothing has been recorded yet.
+    pub fn load_from(path: &Path) -> Result<Statistics, SaveError> {
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Statistics::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), SaveError> {
+        self.save_to(&data_dir()?.join("statistics.json"))
+    }
+
+    pub fn load() -> Result<Statistics, SaveError> {
+        Statistics::load_from(&data_dir()?.join("statistics.json"))
+    }
+}
+
+#[test]
+fn test_record() {
+    let mut record = Record::default();
+
+    record.win(Duration::from_secs(300), 120, Scoring::Standard, 500);
+    record.win(Duration::from_secs(200), 140, Scoring::Standard, 400);
+    record.loss();
+    record.win(Duration::from_secs(250), 100, Scoring::Standard, 450);
+    record.win(Duration::from_secs(400), 130, Scoring::Vegas, 20);
+
+    assert_eq!(5, record.played);
+    assert_eq!(4, record.won);
+    assert_eq!(80.0, record.win_percentage());
+    assert_eq!(2, record.streak);
+    assert_eq!(2, record.longest_streak);
+    assert_eq!(Some(Duration::from_secs(200)), record.best_time);
+    assert_eq!(Some(100), record.fewest_moves);
+    assert_eq!(Some(500), record.best_score);
+    assert_eq!(Some(20), record.best_vegas);
+}
+
+#[test]
+fn test_statistics_to_file() {
+    let path = std::env::temp_dir().join(format!("solitaire_stats_{}", std::process::id())).join("statistics.json");
+
+    assert_eq!(Statistics::default(), Statistics::load_from(&path).unwrap());
+
+    let draw_one = RecordKey::new(VariantKind::Klondike, 1);
+    let freecell = RecordKey::new(VariantKind::FreeCell, 3);
+    let mut statistics = Statistics::default();
+    statistics.record_mut(draw_one).loss();
+    statistics.record_mut(freecell).loss();
+    statistics.record_mut(freecell).loss();
+    statistics.bankroll = -30;
+    statistics.save_to(&path).unwrap();
+
+    let loaded = Statistics::load_from(&path).unwrap();
+    assert_eq!(1, loaded.record(draw_one).played);
+    assert_eq!(0, loaded.record(RecordKey::new(VariantKind::Klondike, 3)).played);
+    // FreeCell has no draw mode
+    assert_eq!(2, loaded.record(RecordKey::new(VariantKind::FreeCell, 1)).played);
+    assert_eq!(-30, loaded.bankroll);
+
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
@@ -0,0 +1,193 @@
+//! Solitaire variants
+//!
+//! `Game` holds the piles and the counters every variant shares, a
+//! `Variant` decides how the cards are dealt onto them and which moves
+//! are allowed.
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::card::{Card, Rank};
+use crate::game::{load_cards, Game, Move, MoveError, MAX_RANDOM_DEAL};
+
+mod freecell;
+mod klondike;
+mod pyramid;
+mod spider;
+mod yukon;
+
+pub use freecell::FreeCell;
+pub use klondike::Klondike;
+pub use pyramid::{row_and_place, Pyramid};
+pub use spider::{Spider, Suits};
+pub use yukon::Yukon;
+
+/// The piles a variant plays with, the board lays them out from this.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub stock: bool,
+    pub waste: bool,
+    pub foundations: usize,
+    pub cells: usize,
+    /// Tableau piles, the slots of the triangle in Pyramid.
+    pub columns: usize,
+    pub shape: Shape,
+}
+
+/// How the tableau piles are set out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shape {
+    /// Side by side, the cards of each fanned downwards.
+    Columns,
+    /// One card per pile in overlapping rows, each card partly covering
+    /// the two in the row above.
+    Pyramid { rows: usize },
+}
+
+/// The rules of one solitaire game.
+pub trait Variant {
+    fn layout(&self) -> Layout;
+
+    /// The deck shuffled for a deal number.
+    fn cards(&self, deal: u32) -> Vec<Card> {
+        load_cards(deal)
+    }
+
+    /// Highest deal number handed out for a random game.
+    fn max_deal(&self) -> u32 {
+        MAX_RANDOM_DEAL
+    }
+
+    /// Puts the cards onto the empty piles of a new game.
+    fn deal(&self, game: &mut Game);
+
+    /// Moves worth checking in the position, `Game::legal_moves` keeps the
+    /// ones that pass `check`.
+    fn candidate_moves(&self, game: &Game) -> Vec<Move>;
+
+    fn check(&self, game: &Game, mv: Move) -> Result<(), MoveError>;
+
+    /// Plays a move that passed `check`.
+    fn apply(&self, game: &mut Game, mv: Move) -> Played;
+
+    /// A legal move that gets the game somewhere, `Game::is_dead` looks for
+    /// these. Drawing and recycling do not count, a card taken off a
+    /// foundation only when something productive can then go onto it, and
+    /// column moves only when they turn a card over, empty a column or free
+    /// a card for a foundation.
+    fn is_productive(&self, game: &Game, mv: Move) -> bool {
+        match mv {
+            Move::StockToWaste | Move::Recycle => false,
+            Move::FoundationToTableau { to, .. } => {
+                builds_on(game, mv, to, |after, next| self.is_productive(after, next))
+            },
+            Move::TableauToTableau { from, depth, to } => {
+                let tab = &game.tab_card_indexes[from];
+                let remaining = tab.len() - depth;
+                if remaining == 0 {
+                    return !game.tab_card_indexes[to].is_empty()
+                }
+                let under = tab[remaining - 1];
+                !game.cards[under].visible
+                    || (0..game.foundation_cards.len()).any(|fd| game.check_foundation(under, fd).is_ok())
+            },
+            _ => true,
+        }
+    }
+
+    /// Whether running out of productive moves means the game is lost, so
+    /// it can be reported and undone back to the last choice.
+    fn finds_lost_games(&self) -> bool {
+        true
+    }
+
+    /// Cards go up one at a time once the columns are sorted, so
+    /// `Game::can_auto_finish` can offer to finish the game.
+    fn auto_finishes(&self) -> bool {
+        true
+    }
+
+    fn is_won(&self, game: &Game) -> bool {
+        game.foundation_cards.iter()
+            .all(|fd| fd.is_some_and(|index| game.cards[index].rank() == Rank::King))
+    }
+}
+
+/// Whether a move that is `productive` can go onto the column once `mv`
+/// is played, looking one move ahead for the ones that only make room.
+/// Taking the cards of `mv` straight back does not count.
+pub fn builds_on(game: &Game, mv: Move, column: usize, productive: impl Fn(&Game, Move) -> bool) -> bool {
+    let mut after = game.clone();
+    game.variant.rules().apply(&mut after, mv);
+    after.legal_moves().into_iter().any(|next| {
+        if let (Move::TableauToTableau { to, .. }, Move::TableauToTableau { from, .. }) = (mv, next) {
+            if from == to {
+                return false
+            }
+        }
+        let onto = match next {
+            Move::WasteToTableau(onto) | Move::TableauToTableau { to: onto, .. }
+                | Move::CellToTableau { to: onto, .. } => onto,
+            _ => return false,
+        };
+        onto == column && productive(&after, next)
+    })
+}
+
+/// What a move did besides moving its own cards, for the scoring.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Played {
+    /// A face down card was turned over.
+    pub flipped: bool,
+    /// Cards the rules took off to the foundations by themselves, the
+    /// finished runs in Spider.
+    pub to_foundations: usize,
+}
+
+/// Names a variant so a game can be saved and picked in the sidebar.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum VariantKind {
+    #[default]
+    Klondike,
+    FreeCell,
+    Spider(Suits),
+    Yukon,
+    Pyramid,
+}
+
+impl VariantKind {
+    pub const ALL: [VariantKind; 7] = [
+        VariantKind::Klondike,
+        VariantKind::FreeCell,
+        VariantKind::Spider(Suits::One),
+        VariantKind::Spider(Suits::Two),
+        VariantKind::Spider(Suits::Four),
+        VariantKind::Yukon,
+        VariantKind::Pyramid,
+    ];
+
+    pub fn rules(self) -> &'static dyn Variant {
+        match self {
+            VariantKind::Klondike => &Klondike,
+            VariantKind::FreeCell => &FreeCell,
+            VariantKind::Spider(Suits::One) => &Spider(Suits::One),
+            VariantKind::Spider(Suits::Two) => &Spider(Suits::Two),
+            VariantKind::Spider(Suits::Four) => &Spider(Suits::Four),
+            VariantKind::Yukon => &Yukon,
+            VariantKind::Pyramid => &Pyramid,
+        }
+    }
+}
+
+impl fmt::Display for VariantKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VariantKind::Klondike => write!(f, "Klondike"),
+            VariantKind::FreeCell => write!(f, "FreeCell"),
+            VariantKind::Spider(Suits::One) => write!(f, "Spider, 1 suit"),
+            VariantKind::Spider(suits) => write!(f, "Spider, {} suits", suits.suits().len()),
+            VariantKind::Yukon => write!(f, "Yukon"),
+            VariantKind::Pyramid => write!(f, "Pyramid"),
+        }
+    }
+}
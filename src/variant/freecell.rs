@@ -0,0 +1,287 @@
+//! FreeCell
+use crate::card::{Card, CardId, Rank, Suit};
+use crate::game::{Game, Move, MoveError};
+use crate::variant::{Layout, Played, Shape, Variant};
+
+/// Highest deal number of the original Microsoft game.
+const MAX_DEAL: u32 = 32_000;
+
+/// Suits in the order the Microsoft deals number them.
+const DEAL_SUITS: [Suit; 4] = [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades];
+
+/// Every card is dealt face up into eight columns, four free cells hold
+/// a card each while the columns are sorted out.
+pub struct FreeCell;
+
+impl Variant for FreeCell {
+    fn layout(&self) -> Layout {
+        Layout {
+            stock: false,
+            waste: false,
+            foundations: 4,
+            cells: 4,
+            shape: Shape::Columns,
+            columns: 8,
+        }
+    }
+
+    /// Shuffles the way the Microsoft game does, so a deal number gives the
+    /// same game people know from it.
+    fn cards(&self, deal: u32) -> Vec<Card> {
+        let mut rng = MsRng(deal);
+        let mut numbers: Vec<usize> = (0..52).collect();
+        let mut cards = Vec::with_capacity(52);
+
+        while !numbers.is_empty() {
+            let number = numbers.swap_remove(rng.next() as usize % numbers.len());
+            cards.push(Card {
+                id: CardId::new(DEAL_SUITS[number % 4], Rank::ALL[number / 4]),
+                visible: true,
+            });
+        }
+
+        cards
+    }
+
+    fn max_deal(&self) -> u32 {
+        MAX_DEAL
+    }
+
+    fn deal(&self, game: &mut Game) {
+        let columns = game.tab_card_indexes.len();
+        for card_index in 0..game.cards.len() {
+            game.cards[card_index].visible = true;
+            game.tab_card_indexes[card_index % columns].push(card_index);
+        }
+    }
+
+    fn candidate_moves(&self, game: &Game) -> Vec<Move> {
+        let mut candidates = vec![];
+        // the free cells are all alike, one of them is enough to try
+        let free_cell = game.cell_cards.iter().position(Option::is_none);
+
+        for (from, tab) in game.tab_card_indexes.iter().enumerate() {
+            for to in 0..game.foundation_cards.len() {
+                candidates.push(Move::TableauToFoundation { from, to });
+            }
+            if let Some(to) = free_cell {
+                candidates.push(Move::TableauToCell { from, to });
+            }
+            for depth in 1..=tab.len() {
+                for to in 0..game.tab_card_indexes.len() {
+                    if to != from {
+                        candidates.push(Move::TableauToTableau { from, depth, to });
+                    }
+                }
+            }
+        }
+
+        for from in 0..game.cell_cards.len() {
+            for to in 0..game.foundation_cards.len() {
+                candidates.push(Move::CellToFoundation { from, to });
+            }
+            for to in 0..game.tab_card_indexes.len() {
+                candidates.push(Move::CellToTableau { from, to });
+            }
+        }
+
+        candidates
+    }
+
+    fn check(&self, game: &Game, mv: Move) -> Result<(), MoveError> {
+        match mv {
+            Move::TableauToTableau { from, depth, to } => {
+                let tab = &game.tab_card_indexes[from];
+                if tab.is_empty() {
+                    return Err(MoveError::EmptySource)
+                }
+                if from == to {
+                    return Err(MoveError::SameColumn)
+                }
+                if depth == 0 || depth > tab.len() {
+                    return Err(MoveError::BadDepth)
+                }
+                let run = &tab[tab.len() - depth..];
+                if run.windows(2).any(|pair| check_on(game, pair[1], pair[0]).is_err()) {
+                    return Err(MoveError::NotSequence)
+                }
+                if let Some(&last) = game.tab_card_indexes[to].last() {
+                    check_on(game, run[0], last)?;
+                }
+                if depth > supermove_size(game, to) {
+                    return Err(MoveError::TooManyCards)
+                }
+                Ok(())
+            },
+            Move::TableauToFoundation { from, to } => {
+                let card_index = *game.tab_card_indexes[from].last().ok_or(MoveError::EmptySource)?;
+                game.check_foundation(card_index, to)
+            },
+            Move::TableauToCell { from, to } => {
+                if game.tab_card_indexes[from].is_empty() {
+                    return Err(MoveError::EmptySource)
+                }
+                if game.cell_cards[to].is_some() {
+                    return Err(MoveError::CellTaken)
+                }
+                Ok(())
+            },
+            Move::CellToTableau { from, to } => {
+                let card_index = game.cell_cards[from].ok_or(MoveError::EmptySource)?;
+                match game.tab_card_indexes[to].last() {
+                    Some(&last) => check_on(game, card_index, last),
+                    None => Ok(()),
+                }
+            },
+            Move::CellToFoundation { from, to } => {
+                let card_index = game.cell_cards[from].ok_or(MoveError::EmptySource)?;
+                game.check_foundation(card_index, to)
+            },
+            Move::StockToWaste | Move::Recycle | Move::WasteToTableau(_) | Move::WasteToFoundation(_)
+                | Move::FoundationToTableau { .. } | Move::DealRow | Move::TableauPair { .. } | Move::WastePair(_) => {
+                Err(MoveError::NotInVariant)
+            },
+        }
+    }
+
+    fn apply(&self, game: &mut Game, mv: Move) -> Played {
+        match mv {
+            Move::TableauToTableau { from, depth, to } => {
+                let split = game.tab_card_indexes[from].len() - depth;
+                let moving_indexes = game.tab_card_indexes[from].split_off(split);
+                game.tab_card_indexes[to].extend(moving_indexes);
+            },
+            Move::TableauToFoundation { from, to } => {
+                game.foundation_cards[to] = game.tab_card_indexes[from].pop();
+            },
+            Move::TableauToCell { from, to } => {
+                game.cell_cards[to] = game.tab_card_indexes[from].pop();
+            },
+            Move::CellToTableau { from, to } => {
+                if let Some(card_index) = game.cell_cards[from].take() {
+                    game.tab_card_indexes[to].push(card_index);
+                }
+            },
+            Move::CellToFoundation { from, to } => {
+                if let Some(card_index) = game.cell_cards[from].take() {
+                    game.foundation_cards[to] = Some(card_index);
+                }
+            },
+            Move::StockToWaste | Move::Recycle | Move::WasteToTableau(_) | Move::WasteToFoundation(_)
+                | Move::FoundationToTableau { .. } | Move::DealRow | Move::TableauPair { .. } | Move::WastePair(_) => (),
+        }
+        // nothing is ever face down
+        Played::default()
+    }
+
+    // cards go round the free cells and empty columns in long chains of
+    // moves that get nowhere one at a time, no single move tells whether
+    // the game can still be won
+    fn finds_lost_games(&self) -> bool {
+        false
+    }
+}
+
+// the card goes on the other one when it is one lower and the other color
+fn check_on(game: &Game, card_index: usize, onto: usize) -> Result<(), MoveError> {
+    let (card, onto) = (&game.cards[card_index], &game.cards[onto]);
+    if card.color() == onto.color() {
+        return Err(MoveError::WrongColor)
+    }
+    if !card.rank().is_below(onto.rank()) {
+        return Err(MoveError::WrongRank)
+    }
+    Ok(())
+}
+
+// cards that can be moved onto the column at once, as if they went one at
+// a time through the free cells and the empty columns other than the
+// destination
+fn supermove_size(game: &Game, to: usize) -> usize {
+    let free_cells = game.cell_cards.iter().filter(|cell| cell.is_none()).count();
+    let empty_columns = game.tab_card_indexes.iter().enumerate()
+        .filter(|(index, tab)| *index != to && tab.is_empty())
+        .count();
+    (free_cells + 1) << empty_columns
+}
+
+// the linear congruential generator of the Microsoft C library
+struct MsRng(u32);
+
+impl MsRng {
+    fn next(&mut self) -> u32 {
+        self.0 = self.0.wrapping_mul(214_013).wrapping_add(2_531_011) & 0x7fff_ffff;
+        self.0 >> 16
+    }
+}
+
+#[test]
+fn test_freecell_deal() {
+    use crate::variant::VariantKind;
+
+    let game = Game::deal(VariantKind::FreeCell, 1, 3);
+
+    let lengths: Vec<usize> = game.tab_card_indexes.iter().map(|tab| tab.len()).collect();
+    assert_eq!(vec![7, 7, 7, 7, 6, 6, 6, 6], lengths);
+    assert!(game.cards.iter().all(|card| card.visible));
+    assert!(game.stock_card_indexes.is_empty());
+    assert_eq!(4, game.cell_cards.len());
+
+    // the first row of Microsoft deal #1
+    let first_row: Vec<String> = game.tab_card_indexes.iter()
+        .map(|tab| game.cards[tab[0]].id.to_string())
+        .collect();
+    assert_eq!(vec!["J♦", "2♦", "9♥", "J♣", "5♦", "7♥", "7♣", "5♥"], first_row);
+}
+
+#[test]
+fn test_supermove() {
+    use crate::card::deck;
+    use crate::variant::VariantKind;
+
+    let id = |suit, rank| CardId::new(suit, rank).index();
+
+    let mut game = Game::new(VariantKind::FreeCell, deck(), 3);
+    game.tab_card_indexes = vec![
+        vec![id(Suit::Spades, Rank::Ten), id(Suit::Hearts, Rank::Nine), id(Suit::Spades, Rank::Eight)],
+        vec![id(Suit::Hearts, Rank::Jack)],
+    ];
+    game.tab_card_indexes.extend((0..6).map(|index| vec![index]));
+    game.cell_cards = (6..10).map(Some).collect();
+
+    let mv = Move::TableauToTableau { from: 0, depth: 3, to: 1 };
+    assert_eq!(Err(MoveError::TooManyCards), game.check(mv));
+    assert_eq!(Err(MoveError::WrongRank), game.check(Move::TableauToTableau { from: 0, depth: 1, to: 1 }));
+
+    // two free cells move three cards
+    game.cell_cards[0] = None;
+    game.cell_cards[1] = None;
+    assert_eq!(Ok(()), game.check(mv));
+
+    // an empty column doubles what one free cell moves
+    game.cell_cards[0] = Some(6);
+    game.tab_card_indexes[7].clear();
+    assert_eq!(4, supermove_size(&game, 1));
+    // but not when the cards are going into it
+    assert_eq!(2, supermove_size(&game, 7));
+
+    game.apply(mv).unwrap();
+    assert_eq!(4, game.tab_card_indexes[1].len());
+    assert!(game.tab_card_indexes[0].is_empty());
+
+    game.apply(Move::CellToTableau { from: 0, to: 7 }).unwrap();
+    game.apply(Move::TableauToCell { from: 7, to: 1 }).unwrap();
+    assert_eq!(Some(6), game.cell_cards[1]);
+    assert_eq!(Err(MoveError::CellTaken), game.check(Move::TableauToCell { from: 2, to: 1 }));
+    assert_eq!(Err(MoveError::NotInVariant), game.check(Move::StockToWaste));
+    assert_eq!(Err(MoveError::BadDepth), game.check(Move::TableauToTableau { from: 2, depth: 9, to: 3 }));
+
+    // no move is left, but that is not taken to mean the game is lost
+    let black = [Suit::Spades, Suit::Clubs];
+    game.tab_card_indexes = [Rank::King, Rank::Queen, Rank::Jack, Rank::Ten].iter()
+        .flat_map(|rank| black.iter().map(|suit| vec![id(*suit, *rank)]))
+        .collect();
+    game.cell_cards = Suit::ALL.iter().map(|suit| Some(id(*suit, Rank::Two))).collect();
+    assert!(game.legal_moves().is_empty());
+    assert!(!game.is_dead());
+}
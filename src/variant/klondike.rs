@@ -0,0 +1,190 @@
+//! Klondike
+use crate::game::{Game, Move, MoveError};
+use crate::variant::{Layout, Played, Shape, Variant};
+
+/// Seven columns dealt 1 to 7 with the top card face up, the rest of the
+/// deck is drawn from the stock one or three cards at a time.
+pub struct Klondike;
+
+impl Variant for Klondike {
+    fn layout(&self) -> Layout {
+        Layout {
+            stock: true,
+            waste: true,
+            foundations: 4,
+            cells: 0,
+            shape: Shape::Columns,
+            columns: 7,
+        }
+    }
+
+    fn deal(&self, game: &mut Game) {
+        let mut card_index = 0;
+        for (i, tab) in game.tab_card_indexes.iter_mut().enumerate() {
+            for j in 0..=i {
+                tab.push(card_index);
+                if j == i {
+                    game.cards[card_index].visible = true;
+                }
+                card_index += 1;
+            }
+        }
+        // add cards left to stock
+        game.stock_card_indexes.extend(card_index..game.cards.len());
+    }
+
+    fn candidate_moves(&self, game: &Game) -> Vec<Move> {
+        let mut candidates = vec![Move::StockToWaste, Move::Recycle];
+
+        for to in 0..game.tab_card_indexes.len() {
+            candidates.push(Move::WasteToTableau(to));
+        }
+        for to in 0..game.foundation_cards.len() {
+            candidates.push(Move::WasteToFoundation(to));
+        }
+
+        for (from, tab) in game.tab_card_indexes.iter().enumerate() {
+            for to in 0..game.foundation_cards.len() {
+                candidates.push(Move::TableauToFoundation { from, to });
+                candidates.push(Move::FoundationToTableau { from: to, to: from });
+            }
+            let face_up = tab.iter().rev().take_while(|index| game.cards[**index].visible).count();
+            for depth in 1..=face_up {
+                for to in 0..game.tab_card_indexes.len() {
+                    if to != from {
+                        candidates.push(Move::TableauToTableau { from, depth, to });
+                    }
+                }
+            }
+        }
+
+        candidates
+    }
+
+    fn check(&self, game: &Game, mv: Move) -> Result<(), MoveError> {
+        match mv {
+            Move::StockToWaste => {
+                if game.stock_card_indexes.is_empty() {
+                    return Err(MoveError::EmptySource)
+                }
+                Ok(())
+            },
+            // move cards back only if stock is empty
+            Move::Recycle => {
+                if !game.stock_card_indexes.is_empty() {
+                    return Err(MoveError::StockNotEmpty)
+                }
+                if game.waste_card_indexes.is_empty() {
+                    return Err(MoveError::EmptySource)
+                }
+                if !game.has_passes_left() {
+                    return Err(MoveError::NoPassesLeft)
+                }
+                Ok(())
+            },
+            Move::WasteToTableau(to) => {
+                let card_index = *game.waste_card_indexes.last().ok_or(MoveError::EmptySource)?;
+                game.check_tableau(card_index, to)
+            },
+            Move::WasteToFoundation(to) => {
+                let card_index = *game.waste_card_indexes.last().ok_or(MoveError::EmptySource)?;
+                game.check_foundation(card_index, to)
+            },
+            Move::TableauToTableau { from, depth, to } => {
+                let tab = &game.tab_card_indexes[from];
+                if tab.is_empty() {
+                    return Err(MoveError::EmptySource)
+                }
+                if from == to {
+                    return Err(MoveError::SameColumn)
+                }
+                if depth == 0 || depth > tab.len() {
+                    return Err(MoveError::BadDepth)
+                }
+                let card_index = tab[tab.len() - depth];
+                if !game.cards[card_index].visible {
+                    return Err(MoveError::FaceDown)
+                }
+                game.check_tableau(card_index, to)
+            },
+            Move::TableauToFoundation { from, to } => {
+                let card_index = *game.tab_card_indexes[from].last().ok_or(MoveError::EmptySource)?;
+                game.check_foundation(card_index, to)
+            },
+            Move::FoundationToTableau { from, to } => {
+                let card_index = game.foundation_cards[from].ok_or(MoveError::EmptySource)?;
+                game.check_tableau(card_index, to)
+            },
+            Move::TableauToCell { .. } | Move::CellToTableau { .. } | Move::CellToFoundation { .. }
+                | Move::DealRow | Move::TableauPair { .. } | Move::WastePair(_) => Err(MoveError::NotInVariant),
+        }
+    }
+
+    fn apply(&self, game: &mut Game, mv: Move) -> Played {
+        let mut flipped = false;
+        match mv {
+            Move::StockToWaste => {
+                let final_length = game.stock_card_indexes.len().saturating_sub(game.cards_to_play);
+                let mut tail = game.stock_card_indexes.split_off(final_length);
+                tail.reverse();
+                game.waste_card_indexes.extend(tail);
+            },
+            Move::Recycle => {
+                game.waste_card_indexes.reverse();
+                game.stock_card_indexes = std::mem::take(&mut game.waste_card_indexes);
+                game.card_rounds += 1;
+                game.cards_moved_from_waste = 0;
+            },
+            Move::WasteToTableau(to) => {
+                if let Some(card_index) = game.waste_card_indexes.pop() {
+                    game.cards[card_index].visible = true;
+                    game.tab_card_indexes[to].push(card_index);
+                    game.cards_moved_from_waste += 1;
+                }
+            },
+            Move::WasteToFoundation(to) => {
+                if let Some(card_index) = game.waste_card_indexes.pop() {
+                    game.foundation_cards[to] = Some(card_index);
+                    game.cards_moved_from_waste += 1;
+                }
+            },
+            Move::TableauToTableau { from, depth, to } => {
+                let split = game.tab_card_indexes[from].len() - depth;
+                let moving_indexes = game.tab_card_indexes[from].split_off(split);
+                game.tab_card_indexes[to].extend(moving_indexes);
+                flipped = game.turn_over_last(from);
+            },
+            Move::TableauToFoundation { from, to } => {
+                if let Some(card_index) = game.tab_card_indexes[from].pop() {
+                    game.foundation_cards[to] = Some(card_index);
+                    flipped = game.turn_over_last(from);
+                }
+            },
+            Move::FoundationToTableau { from, to } => {
+                if let Some(card_index) = game.foundation_cards[from] {
+                    game.foundation_cards[from] = game.card_below(card_index);
+                    game.tab_card_indexes[to].push(card_index);
+                }
+            },
+            Move::TableauToCell { .. } | Move::CellToTableau { .. } | Move::CellToFoundation { .. }
+                | Move::DealRow | Move::TableauPair { .. } | Move::WastePair(_) => (),
+        }
+        Played { flipped, to_foundations: 0 }
+    }
+}
+
+#[test]
+fn test_klondike_deal() {
+    use crate::variant::VariantKind;
+
+    let game = Game::deal(VariantKind::Klondike, 1, 3);
+
+    for (i, tab) in game.tab_card_indexes.iter().enumerate() {
+        assert_eq!(i + 1, tab.len());
+        let visible: Vec<bool> = tab.iter().map(|index| game.cards[*index].visible).collect();
+        assert_eq!(vec![true], visible[i..]);
+        assert!(visible[..i].iter().all(|visible| !visible));
+    }
+    assert_eq!(24, game.stock_card_indexes.len());
+    assert!(game.waste_card_indexes.is_empty());
+}
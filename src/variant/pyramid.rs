@@ -0,0 +1,188 @@
+//! Pyramid
+use crate::card::Rank;
+use crate::game::{Game, Move, MoveError};
+use crate::variant::{Klondike, Layout, Played, Shape, Variant};
+
+/// Rows of the triangle, the bottom one has as many cards.
+const ROWS: usize = 7;
+/// Two cards are removed together when their values add up to this.
+const PAIR_VALUE: u8 = 13;
+
+/// A triangle of 28 cards, each one slot of the tableau, with the rest of
+/// the deck in the stock. Uncovered cards go to the discard pile in pairs
+/// adding up to 13, kings on their own.
+pub struct Pyramid;
+
+impl Variant for Pyramid {
+    fn layout(&self) -> Layout {
+        Layout {
+            stock: true,
+            waste: true,
+            foundations: 1,
+            cells: 0,
+            columns: ROWS * (ROWS + 1) / 2,
+            shape: Shape::Pyramid { rows: ROWS },
+        }
+    }
+
+    fn deal(&self, game: &mut Game) {
+        let slots = game.tab_card_indexes.len().min(game.cards.len());
+        for card_index in 0..slots {
+            game.cards[card_index].visible = true;
+            game.tab_card_indexes[card_index].push(card_index);
+        }
+        game.stock_card_indexes.extend(slots..game.cards.len());
+    }
+
+    fn candidate_moves(&self, game: &Game) -> Vec<Move> {
+        let mut candidates = vec![Move::StockToWaste, Move::Recycle, Move::WasteToFoundation(0)];
+
+        let slots = game.tab_card_indexes.len();
+        for from in 0..slots {
+            candidates.push(Move::TableauToFoundation { from, to: 0 });
+            candidates.push(Move::WastePair(from));
+            for with in from + 1..slots {
+                candidates.push(Move::TableauPair { from, with });
+            }
+        }
+
+        candidates
+    }
+
+    fn check(&self, game: &Game, mv: Move) -> Result<(), MoveError> {
+        match mv {
+            Move::StockToWaste | Move::Recycle => Klondike.check(game, mv),
+            Move::WasteToFoundation(_) => {
+                let card_index = *game.waste_card_indexes.last().ok_or(MoveError::EmptySource)?;
+                check_king(game, card_index)
+            },
+            Move::TableauToFoundation { from, .. } => check_king(game, uncovered(game, from)?),
+            Move::TableauPair { from, with } => {
+                if from == with {
+                    return Err(MoveError::SameColumn)
+                }
+                check_pair(game, uncovered(game, from)?, uncovered(game, with)?)
+            },
+            Move::WastePair(with) => {
+                let card_index = *game.waste_card_indexes.last().ok_or(MoveError::EmptySource)?;
+                check_pair(game, card_index, uncovered(game, with)?)
+            },
+            _ => Err(MoveError::NotInVariant),
+        }
+    }
+
+    // removed cards go onto the discard pile, which shows the last one
+    fn apply(&self, game: &mut Game, mv: Move) -> Played {
+        let removed = match mv {
+            Move::StockToWaste | Move::Recycle => return Klondike.apply(game, mv),
+            Move::WasteToFoundation(_) => game.waste_card_indexes.pop(),
+            Move::TableauToFoundation { from, .. } => game.tab_card_indexes[from].pop(),
+            Move::TableauPair { from, with } => {
+                game.tab_card_indexes[from].pop();
+                game.tab_card_indexes[with].pop()
+            },
+            Move::WastePair(with) => {
+                game.waste_card_indexes.pop();
+                game.tab_card_indexes[with].pop()
+            },
+            _ => None,
+        };
+        if removed.is_some() {
+            game.foundation_cards[0] = removed;
+        }
+        Played::default()
+    }
+
+    // cards leave the triangle in pairs, there is no finishing them off
+    // one at a time
+    fn auto_finishes(&self) -> bool {
+        false
+    }
+
+    /// The triangle is cleared, cards left in the stock do not matter.
+    fn is_won(&self, game: &Game) -> bool {
+        game.tab_card_indexes.iter().all(Vec::is_empty)
+    }
+}
+
+/// The row and the place in the row of a slot of the triangle.
+pub fn row_and_place(slot: usize) -> (usize, usize) {
+    let mut row = 0;
+    while (row + 1) * (row + 2) / 2 <= slot {
+        row += 1;
+    }
+    (row, slot - row * (row + 1) / 2)
+}
+
+// the card in the slot once nothing lies on it, the two slots of the row
+// below cover it
+fn uncovered(game: &Game, slot: usize) -> Result<usize, MoveError> {
+    let card_index = *game.tab_card_indexes[slot].last().ok_or(MoveError::EmptySource)?;
+    let (row, place) = row_and_place(slot);
+    let below = (row + 1) * (row + 2) / 2 + place;
+    if [below, below + 1].iter().any(|slot| game.tab_card_indexes.get(*slot).is_some_and(|tab| !tab.is_empty())) {
+        return Err(MoveError::Covered)
+    }
+    Ok(card_index)
+}
+
+fn check_king(game: &Game, card_index: usize) -> Result<(), MoveError> {
+    if game.cards[card_index].rank() != Rank::King {
+        return Err(MoveError::NotKing)
+    }
+    Ok(())
+}
+
+fn check_pair(game: &Game, first: usize, second: usize) -> Result<(), MoveError> {
+    if game.cards[first].rank().value() + game.cards[second].rank().value() != PAIR_VALUE {
+        return Err(MoveError::NotThirteen)
+    }
+    Ok(())
+}
+
+#[test]
+fn test_pyramid_deal() {
+    use crate::variant::VariantKind;
+
+    let game = Game::deal(VariantKind::Pyramid, 1, 1);
+
+    assert_eq!(28, game.tab_card_indexes.len());
+    assert!(game.tab_card_indexes.iter().all(|tab| tab.len() == 1));
+    assert_eq!(24, game.stock_card_indexes.len());
+
+    assert_eq!((0, 0), row_and_place(0));
+    assert_eq!((2, 1), row_and_place(4));
+    assert_eq!((6, 6), row_and_place(27));
+}
+
+#[test]
+fn test_pyramid_moves() {
+    use crate::card::{deck, CardId, Suit};
+    use crate::score::Scoring;
+    use crate::variant::VariantKind;
+
+    let mut game = Game::new(VariantKind::Pyramid, deck(), 1).with_scoring(Scoring::Vegas);
+    let id = |suit, rank| CardId::new(suit, rank).index();
+
+    // the five of clubs is covered by the nine and the king in the row below
+    game.tab_card_indexes = vec![vec![]; 28];
+    game.tab_card_indexes[1] = vec![id(Suit::Clubs, Rank::Five)];
+    game.tab_card_indexes[3] = vec![id(Suit::Hearts, Rank::Nine)];
+    game.tab_card_indexes[4] = vec![id(Suit::Spades, Rank::King)];
+    game.tab_card_indexes[5] = vec![id(Suit::Hearts, Rank::Eight)];
+    game.waste_card_indexes = vec![id(Suit::Spades, Rank::Four)];
+
+    assert_eq!(Err(MoveError::Covered), game.check(Move::TableauPair { from: 1, with: 5 }));
+    assert_eq!(Err(MoveError::NotThirteen), game.check(Move::WastePair(5)));
+    assert_eq!(Err(MoveError::NotKing), game.check(Move::TableauToFoundation { from: 5, to: 0 }));
+
+    game.apply(Move::TableauToFoundation { from: 4, to: 0 }).unwrap();
+    game.apply(Move::WastePair(3)).unwrap();
+    assert_eq!(Some(id(Suit::Hearts, Rank::Nine)), game.foundation_cards[0]);
+
+    // the five is uncovered now
+    game.apply(Move::TableauPair { from: 1, with: 5 }).unwrap();
+    assert!(game.is_won());
+    // five cards gone at $5 each, after buying the deck
+    assert_eq!(-52 + 25, game.score);
+}
@@ -0,0 +1,270 @@
+//! Spider
+use serde::{Deserialize, Serialize};
+
+use crate::card::{Card, CardId, Rank, Suit};
+use crate::game::{shuffle, Game, Move, MoveError};
+use crate::variant::{builds_on, Layout, Played, Shape, Variant};
+
+/// Cards in a Spider deal, two decks whatever the suits.
+const DECK_SIZE: usize = 104;
+/// Cards dealt to the columns at the start, the rest wait in the stock.
+const DEALT: usize = 54;
+/// A king down to an ace of one suit, taken off as soon as it is built.
+const RUN: usize = 13;
+
+/// How many suits the two decks are made of, fewer is easier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Suits {
+    One,
+    Two,
+    Four,
+}
+
+impl Suits {
+    pub fn suits(self) -> &'static [Suit] {
+        match self {
+            Suits::One => &[Suit::Spades],
+            Suits::Two => &[Suit::Spades, Suit::Hearts],
+            Suits::Four => &Suit::ALL,
+        }
+    }
+}
+
+/// Ten columns built down regardless of suit, only a run of one suit moves
+/// together. The stock deals a card onto every column at once.
+pub struct Spider(pub Suits);
+
+impl Variant for Spider {
+    fn layout(&self) -> Layout {
+        Layout {
+            stock: true,
+            waste: false,
+            foundations: 8,
+            cells: 0,
+            shape: Shape::Columns,
+            columns: 10,
+        }
+    }
+
+    fn cards(&self, deal: u32) -> Vec<Card> {
+        let suits = self.0.suits();
+        let cards = (0..DECK_SIZE)
+            .map(|i| Card {
+                id: CardId::new(suits[i / RUN % suits.len()], Rank::ALL[i % RUN]),
+                visible: false,
+            })
+            .collect();
+        shuffle(cards, deal)
+    }
+
+    fn deal(&self, game: &mut Game) {
+        let columns = game.tab_card_indexes.len();
+        for card_index in 0..DEALT.min(game.cards.len()) {
+            game.tab_card_indexes[card_index % columns].push(card_index);
+        }
+        for tab in 0..columns {
+            game.turn_over_last(tab);
+        }
+        game.stock_card_indexes.extend(DEALT..game.cards.len());
+    }
+
+    fn candidate_moves(&self, game: &Game) -> Vec<Move> {
+        let mut candidates = vec![Move::DealRow];
+
+        for (from, tab) in game.tab_card_indexes.iter().enumerate() {
+            let face_up = tab.iter().rev().take_while(|index| game.cards[**index].visible).count();
+            for depth in 1..=face_up {
+                for to in 0..game.tab_card_indexes.len() {
+                    if to != from {
+                        candidates.push(Move::TableauToTableau { from, depth, to });
+                    }
+                }
+            }
+        }
+
+        candidates
+    }
+
+    fn check(&self, game: &Game, mv: Move) -> Result<(), MoveError> {
+        match mv {
+            Move::DealRow => {
+                if game.stock_card_indexes.is_empty() {
+                    return Err(MoveError::EmptySource)
+                }
+                if game.tab_card_indexes.iter().any(Vec::is_empty) {
+                    return Err(MoveError::EmptyColumn)
+                }
+                Ok(())
+            },
+            Move::TableauToTableau { from, depth, to } => {
+                let tab = &game.tab_card_indexes[from];
+                if tab.is_empty() {
+                    return Err(MoveError::EmptySource)
+                }
+                if from == to {
+                    return Err(MoveError::SameColumn)
+                }
+                if depth == 0 || depth > tab.len() {
+                    return Err(MoveError::BadDepth)
+                }
+                let run = &tab[tab.len() - depth..];
+                if !game.cards[run[0]].visible {
+                    return Err(MoveError::FaceDown)
+                }
+                if !is_run(game, run) {
+                    return Err(MoveError::NotSequence)
+                }
+                // any suit builds, but only on the next rank up
+                match game.tab_card_indexes[to].last() {
+                    Some(&last) if !game.cards[run[0]].rank().is_below(game.cards[last].rank()) => {
+                        Err(MoveError::WrongRank)
+                    },
+                    _ => Ok(()),
+                }
+            },
+            _ => Err(MoveError::NotInVariant),
+        }
+    }
+
+    fn apply(&self, game: &mut Game, mv: Move) -> Played {
+        let mut played = Played::default();
+        match mv {
+            Move::DealRow => {
+                for tab in 0..game.tab_card_indexes.len() {
+                    if let Some(card_index) = game.stock_card_indexes.pop() {
+                        game.cards[card_index].visible = true;
+                        game.tab_card_indexes[tab].push(card_index);
+                    }
+                    if remove_run(game, tab) {
+                        played.to_foundations += RUN;
+                        played.flipped |= game.turn_over_last(tab);
+                    }
+                }
+            },
+            Move::TableauToTableau { from, depth, to } => {
+                let split = game.tab_card_indexes[from].len() - depth;
+                let moving_indexes = game.tab_card_indexes[from].split_off(split);
+                game.tab_card_indexes[to].extend(moving_indexes);
+                played.flipped = game.turn_over_last(from);
+                if remove_run(game, to) {
+                    played.to_foundations += RUN;
+                    played.flipped |= game.turn_over_last(to);
+                }
+            },
+            _ => (),
+        }
+        played
+    }
+
+    // a run moved off a face up card also counts when the card it leaves
+    // on top can then take a run that improves things
+    fn is_productive(&self, game: &Game, mv: Move) -> bool {
+        improves(game, mv)
+            || matches!(mv, Move::TableauToTableau { from, .. } if builds_on(game, mv, from, improves))
+    }
+}
+
+// dealing a row, or a column move that turns a card over, empties its
+// column onto another or puts a run on its own suit when it was not before
+fn improves(game: &Game, mv: Move) -> bool {
+    let Move::TableauToTableau { from, depth, to } = mv else {
+        return mv == Move::DealRow
+    };
+    let tab = &game.tab_card_indexes[from];
+    let remaining = tab.len() - depth;
+    if remaining == 0 {
+        return !game.tab_card_indexes[to].is_empty()
+    }
+    let (moving, under) = (tab[remaining], tab[remaining - 1]);
+    let in_suit = |onto: usize| is_run(game, &[onto, moving]);
+    !game.cards[under].visible
+        || (game.tab_card_indexes[to].last().is_some_and(|last| in_suit(*last)) && !in_suit(under))
+}
+
+// face up cards going down one rank at a time in the same suit
+fn is_run(game: &Game, cards: &[usize]) -> bool {
+    cards.iter().all(|index| game.cards[*index].visible)
+        && cards.windows(2).all(|pair| {
+            let (upper, lower) = (&game.cards[pair[0]], &game.cards[pair[1]]);
+            upper.suit() == lower.suit() && lower.rank().is_below(upper.rank())
+        })
+}
+
+// takes a finished king to ace run off the bottom of the column and onto
+// the next empty foundation, which shows its king, true when one was
+fn remove_run(game: &mut Game, tab: usize) -> bool {
+    let column = &game.tab_card_indexes[tab];
+    let Some(start) = column.len().checked_sub(RUN) else {
+        return false
+    };
+    let king = column[start];
+    if game.cards[king].rank() != Rank::King || !is_run(game, &column[start..]) {
+        return false
+    }
+
+    game.tab_card_indexes[tab].truncate(start);
+    if let Some(fd) = game.foundation_cards.iter().position(Option::is_none) {
+        game.foundation_cards[fd] = Some(king);
+    }
+    true
+}
+
+#[test]
+fn test_spider_deal() {
+    use crate::variant::VariantKind;
+
+    let game = Game::deal(VariantKind::Spider(Suits::Two), 1, 3);
+
+    assert_eq!(DECK_SIZE, game.cards.len());
+    let spades = game.cards.iter().filter(|card| card.suit() == Suit::Spades).count();
+    assert_eq!(DECK_SIZE / 2, spades);
+
+    let lengths: Vec<usize> = game.tab_card_indexes.iter().map(|tab| tab.len()).collect();
+    assert_eq!(vec![6, 6, 6, 6, 5, 5, 5, 5, 5, 5], lengths);
+    assert_eq!(50, game.stock_card_indexes.len());
+    assert_eq!(10, game.cards.iter().filter(|card| card.visible).count());
+}
+
+#[test]
+fn test_spider_runs() {
+    use crate::card::deck;
+    use crate::score::Scoring;
+    use crate::variant::VariantKind;
+
+    let mut game = Game::new(VariantKind::Spider(Suits::One), deck(), 3);
+    for card in game.cards.iter_mut() {
+        card.visible = true;
+    }
+    let spade = |rank: Rank| CardId::new(Suit::Spades, rank).index();
+    let heart = |rank: Rank| CardId::new(Suit::Hearts, rank).index();
+
+    // a king down to the two of spades and an ace to finish it on
+    game.tab_card_indexes = vec![vec![]; 10];
+    game.tab_card_indexes[0] = vec![heart(Rank::Five)];
+    game.tab_card_indexes[0].extend(Rank::ALL[1..].iter().rev().map(|rank| spade(*rank)));
+    game.tab_card_indexes[1] = vec![spade(Rank::Ace)];
+    game.tab_card_indexes[2] = vec![heart(Rank::Three), spade(Rank::Two)];
+    game.stock_card_indexes = vec![heart(Rank::Six)];
+
+    // only a run of one suit moves together
+    assert_eq!(Err(MoveError::NotSequence), game.check(Move::TableauToTableau { from: 2, depth: 2, to: 3 }));
+    assert_eq!(Err(MoveError::WrongRank), game.check(Move::TableauToTableau { from: 2, depth: 1, to: 1 }));
+    assert_eq!(Err(MoveError::EmptyColumn), game.check(Move::DealRow));
+    assert_eq!(Err(MoveError::BadDepth), game.check(Move::TableauToTableau { from: 2, depth: 3, to: 3 }));
+
+    // the finished run pays for each of its cards
+    game.scoring = Scoring::Vegas;
+    game.score = 0;
+    game.apply(Move::TableauToTableau { from: 1, depth: 1, to: 0 }).unwrap();
+    assert_eq!(vec![heart(Rank::Five)], game.tab_card_indexes[0]);
+    assert_eq!(Some(spade(Rank::King)), game.foundation_cards[0]);
+    assert!(game.foundation_cards[1..].iter().all(Option::is_none));
+    assert_eq!(5 * RUN as i32, game.score);
+
+    // the six of hearts joins its own suit, moving it back undoes that
+    game.tab_card_indexes[1] = vec![spade(Rank::Seven), heart(Rank::Six)];
+    game.tab_card_indexes[2] = vec![heart(Rank::Seven)];
+    assert!(Spider(Suits::One).is_productive(&game, Move::TableauToTableau { from: 1, depth: 1, to: 2 }));
+    game.apply(Move::TableauToTableau { from: 1, depth: 1, to: 2 }).unwrap();
+    assert!(!Spider(Suits::One).is_productive(&game, Move::TableauToTableau { from: 2, depth: 1, to: 1 }));
+}
@@ -0,0 +1,130 @@
+//! Yukon
+use crate::game::{Game, Move, MoveError};
+use crate::variant::{builds_on, Klondike, Layout, Played, Shape, Variant};
+
+/// Face up cards dealt onto each column after the first, on top of the
+/// face down ones.
+const FACE_UP: usize = 5;
+
+/// Klondike's columns with the whole deck dealt out and no stock. Any face
+/// up card moves together with everything on top of it, in sequence or not.
+pub struct Yukon;
+
+impl Variant for Yukon {
+    fn layout(&self) -> Layout {
+        Layout {
+            stock: false,
+            waste: false,
+            foundations: 4,
+            cells: 0,
+            shape: Shape::Columns,
+            columns: 7,
+        }
+    }
+
+    // the first column gets a single card, column i gets i face down
+    // cards and five face up ones
+    fn deal(&self, game: &mut Game) {
+        let mut card_index = 0;
+        for (i, tab) in game.tab_card_indexes.iter_mut().enumerate() {
+            let face_up = if i == 0 { 1 } else { FACE_UP };
+            for j in 0..i + face_up {
+                if card_index == game.cards.len() {
+                    return
+                }
+                tab.push(card_index);
+                game.cards[card_index].visible = j >= i;
+                card_index += 1;
+            }
+        }
+    }
+
+    // Klondike already tries every face up card of a column, Yukon only
+    // leaves out the stock
+    fn candidate_moves(&self, game: &Game) -> Vec<Move> {
+        Klondike.candidate_moves(game).into_iter()
+            .filter(|mv| is_tableau_move(*mv))
+            .collect()
+    }
+
+    // the cards on top of the one moved are never looked at, so the
+    // Klondike rules for the card itself are all Yukon needs
+    fn check(&self, game: &Game, mv: Move) -> Result<(), MoveError> {
+        if !is_tableau_move(mv) {
+            return Err(MoveError::NotInVariant)
+        }
+        Klondike.check(game, mv)
+    }
+
+    fn apply(&self, game: &mut Game, mv: Move) -> Played {
+        Klondike.apply(game, mv)
+    }
+
+    // any face up card moves with what is on top of it, so a group is
+    // mostly moved off a face up card to build on that card next
+    fn is_productive(&self, game: &Game, mv: Move) -> bool {
+        Klondike.is_productive(game, mv)
+            || matches!(mv, Move::TableauToTableau { from, .. }
+                if builds_on(game, mv, from, |after, next| Klondike.is_productive(after, next)))
+    }
+}
+
+fn is_tableau_move(mv: Move) -> bool {
+    matches!(mv, Move::TableauToTableau { .. } | Move::TableauToFoundation { .. } | Move::FoundationToTableau { .. })
+}
+
+#[test]
+fn test_yukon_deal() {
+    use crate::variant::VariantKind;
+
+    let game = Game::deal(VariantKind::Yukon, 1, 3);
+
+    let lengths: Vec<usize> = game.tab_card_indexes.iter().map(|tab| tab.len()).collect();
+    assert_eq!(vec![1, 6, 7, 8, 9, 10, 11], lengths);
+    for (i, tab) in game.tab_card_indexes.iter().enumerate() {
+        let face_down = tab.iter().filter(|index| !game.cards[**index].visible).count();
+        assert_eq!(i, face_down);
+    }
+    assert!(game.stock_card_indexes.is_empty());
+}
+
+#[test]
+fn test_yukon_moves() {
+    use crate::card::{deck, CardId, Rank, Suit};
+    use crate::variant::VariantKind;
+
+    let mut game = Game::new(VariantKind::Yukon, deck(), 3);
+    for card in game.cards.iter_mut() {
+        card.visible = true;
+    }
+    let id = |suit, rank| CardId::new(suit, rank).index();
+
+    // the nine of hearts carries two cards that are not in sequence with it
+    game.tab_card_indexes = vec![vec![]; 7];
+    game.tab_card_indexes[0] = vec![id(Suit::Clubs, Rank::Two), id(Suit::Hearts, Rank::Nine),
+                                    id(Suit::Spades, Rank::Four), id(Suit::Diamonds, Rank::King)];
+    game.tab_card_indexes[1] = vec![id(Suit::Spades, Rank::Ten)];
+
+    let mv = Move::TableauToTableau { from: 0, depth: 3, to: 1 };
+    assert_eq!(Some(&mv), game.legal_moves().iter().find(|legal| **legal == mv));
+    game.apply(mv).unwrap();
+    assert_eq!(4, game.tab_card_indexes[1].len());
+    assert_eq!(vec![id(Suit::Clubs, Rank::Two)], game.tab_card_indexes[0]);
+
+    // only the card moved has to fit
+    assert_eq!(Err(MoveError::WrongColor), game.check(Move::TableauToTableau { from: 1, depth: 2, to: 0 }));
+    assert_eq!(Err(MoveError::NotInVariant), game.check(Move::StockToWaste));
+
+    // the nine moves off the five so the four can go on it and turn a card over
+    game.tab_card_indexes[0] = vec![id(Suit::Diamonds, Rank::Ace), id(Suit::Hearts, Rank::Five), id(Suit::Clubs, Rank::Nine)];
+    game.tab_card_indexes[1] = vec![id(Suit::Hearts, Rank::Ace), id(Suit::Spades, Rank::Four)];
+    game.tab_card_indexes[2] = vec![id(Suit::Hearts, Rank::Ten)];
+    game.cards[id(Suit::Diamonds, Rank::Ace)].visible = false;
+    game.cards[id(Suit::Hearts, Rank::Ace)].visible = false;
+    let mv = Move::TableauToTableau { from: 0, depth: 1, to: 2 };
+    assert!(game.productive_moves().contains(&mv));
+
+    // with nothing to go on the five it is just a shuffle
+    game.tab_card_indexes[1].pop();
+    assert!(!game.productive_moves().contains(&mv));
+}